@@ -0,0 +1,32 @@
+//! Compares creating small short-lived secrets with pages of their own against
+//! creating them in a [`SecretPool`].
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use shush::{ExposeSecret, SecretBox, SecretPool};
+use std::hint::black_box;
+
+fn small_secrets(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small secret");
+
+    group.bench_function("own pages", |b| {
+        b.iter_batched(
+            || Box::new([7u8; 32]),
+            |key| black_box(SecretBox::new(key)).expose_secret()[0],
+            BatchSize::SmallInput,
+        )
+    });
+
+    let pool = SecretPool::new(32, 64);
+    group.bench_function("pooled", |b| {
+        b.iter_batched(
+            || Box::new([7u8; 32]),
+            |key| black_box(SecretBox::new_in(key, &pool)).expose_secret()[0],
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, small_secrets);
+criterion_main!(benches);
@@ -0,0 +1,206 @@
+//! Best-effort `madvise(2)` hints for the pages holding a secret.
+//!
+//! Hints apply to whole pages, so they also cover any other data sharing the first and
+//! last page of the secret. Every hint returns whether it succeeded.
+//!
+//! macOS has neither `MADV_DONTDUMP` nor `MADV_DONTFORK`. Pages are kept out of forks
+//! with `minherit(2)` instead, and there is no way to exclude single pages from core
+//! dumps, only to disable them for the whole process with `RLIMIT_CORE`, so that hint
+//! is skipped.
+
+/// Exclude the pages holding `len` bytes at `ptr` from core dumps.
+///
+/// # Safety
+///
+/// `ptr` must point to an allocation of at least `len` bytes.
+pub(crate) unsafe fn exclude_from_dump(ptr: *mut u8, len: usize) -> bool {
+    imp::exclude_from_dump(ptr, len)
+}
+
+/// Revert [`exclude_from_dump`] before the pages are freed.
+///
+/// # Safety
+///
+/// `ptr` must point to an allocation of at least `len` bytes.
+pub(crate) unsafe fn include_in_dump(ptr: *mut u8, len: usize) -> bool {
+    imp::include_in_dump(ptr, len)
+}
+
+/// Keep the pages holding `len` bytes at `ptr` out of child processes created by `fork`.
+///
+/// Only applied on Linux and macOS with the `dontfork` feature, since the child also
+/// loses every other value sharing those pages.
+///
+/// # Safety
+///
+/// `ptr` must point to an allocation of at least `len` bytes.
+pub(crate) unsafe fn exclude_from_fork(ptr: *mut u8, len: usize) -> bool {
+    #[cfg(all(feature = "dontfork", target_os = "linux"))]
+    return imp::madvise(ptr, len, libc::MADV_DONTFORK);
+
+    #[cfg(all(feature = "dontfork", target_os = "macos"))]
+    return imp::inherit(ptr, len, imp::VM_INHERIT_NONE);
+
+    #[cfg(not(all(feature = "dontfork", any(target_os = "linux", target_os = "macos"))))]
+    {
+        let _ = (ptr, len);
+        true
+    }
+}
+
+/// Revert [`exclude_from_fork`] before the pages are freed.
+///
+/// # Safety
+///
+/// `ptr` must point to an allocation of at least `len` bytes.
+pub(crate) unsafe fn include_in_fork(ptr: *mut u8, len: usize) -> bool {
+    #[cfg(all(feature = "dontfork", target_os = "linux"))]
+    return imp::madvise(ptr, len, libc::MADV_DOFORK);
+
+    #[cfg(all(feature = "dontfork", target_os = "macos"))]
+    return imp::inherit(ptr, len, imp::VM_INHERIT_COPY);
+
+    #[cfg(not(all(feature = "dontfork", any(target_os = "linux", target_os = "macos"))))]
+    {
+        let _ = (ptr, len);
+        true
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
+mod imp {
+    use super::enclosing_pages;
+
+    #[cfg(target_os = "linux")]
+    const DONT_DUMP: libc::c_int = libc::MADV_DONTDUMP;
+    #[cfg(target_os = "linux")]
+    const DO_DUMP: libc::c_int = libc::MADV_DODUMP;
+    #[cfg(not(target_os = "linux"))]
+    const DONT_DUMP: libc::c_int = libc::MADV_NOCORE;
+    #[cfg(not(target_os = "linux"))]
+    const DO_DUMP: libc::c_int = libc::MADV_CORE;
+
+    pub(super) unsafe fn exclude_from_dump(ptr: *mut u8, len: usize) -> bool {
+        madvise(ptr, len, DONT_DUMP)
+    }
+
+    pub(super) unsafe fn include_in_dump(ptr: *mut u8, len: usize) -> bool {
+        madvise(ptr, len, DO_DUMP)
+    }
+
+    pub(super) unsafe fn madvise(ptr: *mut u8, len: usize, advice: libc::c_int) -> bool {
+        if len == 0 {
+            return true;
+        }
+
+        let (start, len) = enclosing_pages(ptr, len);
+
+        libc::madvise(start, len, advice) == 0
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    #[cfg(feature = "dontfork")]
+    use super::enclosing_pages;
+
+    // From `<mach/vm_inherit.h>`.
+    #[cfg(feature = "dontfork")]
+    pub(super) const VM_INHERIT_COPY: libc::c_int = 1;
+    #[cfg(feature = "dontfork")]
+    pub(super) const VM_INHERIT_NONE: libc::c_int = 2;
+
+    #[cfg(feature = "dontfork")]
+    extern "C" {
+        fn minherit(
+            addr: *mut libc::c_void,
+            len: libc::size_t,
+            inherit: libc::c_int,
+        ) -> libc::c_int;
+    }
+
+    pub(super) unsafe fn exclude_from_dump(_ptr: *mut u8, _len: usize) -> bool {
+        true
+    }
+
+    pub(super) unsafe fn include_in_dump(_ptr: *mut u8, _len: usize) -> bool {
+        true
+    }
+
+    #[cfg(feature = "dontfork")]
+    pub(super) unsafe fn inherit(ptr: *mut u8, len: usize, inherit: libc::c_int) -> bool {
+        if len == 0 {
+            return true;
+        }
+
+        let (start, len) = enclosing_pages(ptr, len);
+
+        minherit(start, len, inherit) == 0
+    }
+}
+
+/// Widen `len` bytes at `ptr` to the pages holding them, since page hints only accept
+/// page-aligned addresses.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    all(target_os = "macos", feature = "dontfork")
+))]
+fn enclosing_pages(ptr: *mut u8, len: usize) -> (*mut libc::c_void, usize) {
+    let page_size = crate::page::page_size();
+    let start = ptr as usize & !(page_size - 1);
+    let end = (ptr as usize + len).next_multiple_of(page_size);
+
+    (start as *mut libc::c_void, end - start)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "macos"
+)))]
+mod imp {
+    pub(super) unsafe fn exclude_from_dump(_ptr: *mut u8, _len: usize) -> bool {
+        true
+    }
+
+    pub(super) unsafe fn include_in_dump(_ptr: *mut u8, _len: usize) -> bool {
+        true
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclude_small_secret_from_dump() {
+        let mut secret = Box::new([1u8; 32]);
+        let ptr = secret.as_mut_ptr();
+
+        unsafe {
+            assert!(exclude_from_dump(ptr, secret.len()));
+            assert!(include_in_dump(ptr, secret.len()));
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "macos"))]
+mod macos_tests {
+    use super::*;
+
+    #[test]
+    fn test_advise_small_secret() {
+        let mut secret = Box::new([1u8; 32]);
+        let ptr = secret.as_mut_ptr();
+
+        unsafe {
+            assert!(exclude_from_dump(ptr, secret.len()));
+            assert!(exclude_from_fork(ptr, secret.len()));
+            assert!(include_in_fork(ptr, secret.len()));
+            assert!(include_in_dump(ptr, secret.len()));
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! Secrets shared between threads without copying them.
+
+use crate::{ExposeSecret, LockError, SecretBox, SecretGuard};
+use alloc::{boxed::Box, sync::Arc};
+use core::{
+    any,
+    fmt::{self, Debug},
+    hint,
+    marker::PhantomData,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+use zeroize::Zeroize;
+
+/// Reference-counted secret, sharing one locked allocation between its clones.
+///
+/// Cloning a `SecretArc` only bumps the reference count, unlike cloning a
+/// [`SecretBox`] which locks a new copy. The secret is zeroized and unlocked once the
+/// last clone is dropped. It can only be exposed immutably, so every clone can read it
+/// concurrently.
+///
+/// A shared protected secret, see [`SecretArc::new_protected`], counts the guards of
+/// all its clones together: its pages stay readable while any of them is alive and
+/// become inaccessible once the last one is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use shush::SecretArc;
+/// use std::thread;
+///
+/// let key = SecretArc::new(Box::new([7u8; 32]));
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let key = key.clone();
+///         thread::spawn(move || key.expose_secret()[0])
+///     })
+///     .collect();
+///
+/// for handle in handles {
+///     assert_eq!(handle.join().unwrap(), 7);
+/// }
+/// ```
+pub struct SecretArc<S: Zeroize>(Arc<SecretBox<S>>);
+
+impl<S: Zeroize> SecretArc<S> {
+    /// Create a shared secret using a pre-boxed value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see [`Self::try_new`]
+    /// for a fallible version.
+    pub fn new(boxed_secret: Box<S>) -> Self {
+        Self::from(SecretBox::new(boxed_secret))
+    }
+
+    /// Create a shared secret using a pre-boxed value, returning an error if its
+    /// memory cannot be locked.
+    pub fn try_new(boxed_secret: Box<S>) -> Result<Self, LockError> {
+        SecretBox::try_new(boxed_secret).map(Self::from)
+    }
+
+    /// Create a shared secret whose pages are inaccessible while no guard exposes it,
+    /// see [`SecretBox::new_protected`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see
+    /// [`Self::try_new_protected`] for a fallible version.
+    pub fn new_protected(boxed_secret: Box<S>) -> Self {
+        Self::from(SecretBox::new_protected(boxed_secret))
+    }
+
+    /// Same as [`Self::new_protected`], but returns an error if the memory of the secret
+    /// cannot be locked.
+    pub fn try_new_protected(boxed_secret: Box<S>) -> Result<Self, LockError> {
+        SecretBox::try_new_protected(boxed_secret).map(Self::from)
+    }
+
+    /// Expose the secret as non-mutable.
+    pub fn expose_secret(&self) -> SecretGuard<'_, S> {
+        self.0.expose_secret()
+    }
+
+    /// Expose the secret to `f` and return whatever it returns, see
+    /// [`SecretBox::with_secret`].
+    pub fn with_secret<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        self.0.with_secret(f)
+    }
+
+    /// Number of clones sharing the secret, including this one.
+    pub fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(&this.0)
+    }
+}
+
+impl<S: Zeroize> From<SecretBox<S>> for SecretArc<S> {
+    /// Share an existing secret, keeping its locked allocation.
+    fn from(secret: SecretBox<S>) -> Self {
+        Self(Arc::new(secret))
+    }
+}
+
+impl<S: Zeroize> Clone for SecretArc<S> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<S: Zeroize> Debug for SecretArc<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretArc<{}>({})", any::type_name::<S>(), self.0.debug_label)
+    }
+}
+
+/// Shared handle to a secret that can be replaced atomically, e.g. to rotate a key that
+/// many threads read, akin to the `arc-swap` crate.
+///
+/// [`Self::load`] returns the current secret as a [`SecretArc`], which keeps it alive
+/// and locked while the reader uses it. [`Self::store`] replaces it with a new secret
+/// in one atomic step, so a reader sees either the old or the new secret, never a mix
+/// of both. The old secret is zeroized and unlocked once the last reader drops it.
+///
+/// Loading never waits. Replacing the secret waits for loads in flight, which only
+/// take a reference count, so it can spin while other threads load constantly.
+///
+/// # Examples
+///
+/// ```
+/// use shush::{SecretArcSwap, SecretBox};
+/// use std::{sync::Arc, thread};
+///
+/// let key = Arc::new(SecretArcSwap::new(Box::new([1u8; 32])));
+/// let reader = key.load();
+///
+/// let shared = Arc::clone(&key);
+/// thread::spawn(move || shared.store(SecretBox::new(Box::new([2; 32]))))
+///     .join()
+///     .unwrap();
+///
+/// assert_eq!(*reader.expose_secret(), [1; 32]);
+/// assert_eq!(*key.load().expose_secret(), [2; 32]);
+/// ```
+pub struct SecretArcSwap<S: Zeroize> {
+    /// Pointer from [`Arc::into_raw`], owning one reference to the current secret.
+    current: AtomicPtr<SecretBox<S>>,
+    /// Number of [`Self::load`] calls between reading `current` and taking a reference.
+    loading: AtomicUsize,
+    secret: PhantomData<Arc<SecretBox<S>>>,
+}
+
+impl<S: Zeroize> SecretArcSwap<S> {
+    /// Create a swappable secret using a pre-boxed value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see [`Self::try_new`]
+    /// for a fallible version.
+    pub fn new(boxed_secret: Box<S>) -> Self {
+        Self::from(SecretBox::new(boxed_secret))
+    }
+
+    /// Create a swappable secret using a pre-boxed value, returning an error if its
+    /// memory cannot be locked.
+    pub fn try_new(boxed_secret: Box<S>) -> Result<Self, LockError> {
+        SecretBox::try_new(boxed_secret).map(Self::from)
+    }
+
+    /// Share the current secret, which stays alive even if it is replaced meanwhile.
+    pub fn load(&self) -> SecretArc<S> {
+        self.loading.fetch_add(1, Ordering::SeqCst);
+
+        // Replacing the secret keeps the reference of `current` until no load is in
+        // flight, so the pointer is still valid here.
+        let current = self.current.load(Ordering::SeqCst);
+        unsafe { Arc::increment_strong_count(current) };
+
+        self.loading.fetch_sub(1, Ordering::SeqCst);
+
+        SecretArc(unsafe { Arc::from_raw(current) })
+    }
+
+    /// Replace the secret, zeroizing and unlocking the old one once no reader holds it.
+    pub fn store(&self, secret: SecretBox<S>) {
+        drop(self.swap(secret));
+    }
+
+    /// Replace the secret and return the old one.
+    pub fn swap(&self, secret: SecretBox<S>) -> SecretArc<S> {
+        let old = self.current.swap(into_raw(secret), Ordering::SeqCst);
+
+        unsafe { self.release(old) }
+    }
+
+    /// Replace the secret only if it still is `current`, e.g. as loaded before deriving
+    /// the new secret from it, and return the old one.
+    ///
+    /// Returns `secret` back if another secret was stored meanwhile.
+    // The handle is handed back as is, the secret itself is never moved.
+    #[allow(clippy::result_large_err)]
+    pub fn compare_and_swap(
+        &self,
+        current: &SecretArc<S>,
+        secret: SecretBox<S>,
+    ) -> Result<SecretArc<S>, SecretBox<S>> {
+        let current = Arc::as_ptr(&current.0).cast_mut();
+        let new = into_raw(secret);
+
+        match self
+            .current
+            .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
+        {
+            Ok(old) => Ok(unsafe { self.release(old) }),
+            Err(_) => {
+                // `new` was never shared, so this is its only reference.
+                let new = unsafe { Arc::from_raw(new) };
+                Err(Arc::into_inner(new).expect("secret is shared"))
+            }
+        }
+    }
+
+    /// Take over the reference of `old`, a pointer just swapped out of `current`, once
+    /// loads that may have read it took their own.
+    ///
+    /// # Safety
+    ///
+    /// `old` must come from [`into_raw`] and not be in `current` anymore.
+    unsafe fn release(&self, old: *mut SecretBox<S>) -> SecretArc<S> {
+        while self.loading.load(Ordering::SeqCst) != 0 {
+            hint::spin_loop();
+        }
+
+        SecretArc(Arc::from_raw(old))
+    }
+}
+
+/// Share `secret`, returning a pointer that owns one reference to it.
+fn into_raw<S: Zeroize>(secret: SecretBox<S>) -> *mut SecretBox<S> {
+    Arc::into_raw(Arc::new(secret)).cast_mut()
+}
+
+impl<S: Zeroize> From<SecretBox<S>> for SecretArcSwap<S> {
+    /// Make an existing secret swappable, keeping its locked allocation.
+    fn from(secret: SecretBox<S>) -> Self {
+        Self {
+            current: AtomicPtr::new(into_raw(secret)),
+            loading: AtomicUsize::new(0),
+            secret: PhantomData,
+        }
+    }
+}
+
+impl<S: Zeroize> Drop for SecretArcSwap<S> {
+    fn drop(&mut self) {
+        drop(unsafe { Arc::from_raw(*self.current.get_mut()) });
+    }
+}
+
+impl<S: Zeroize> Debug for SecretArcSwap<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretArcSwap<{}>({})", any::type_name::<S>(), self.load().0.debug_label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    static ZEROIZED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted([u8; 16]);
+
+    impl Zeroize for Counted {
+        fn zeroize(&mut self) {
+            ZEROIZED.fetch_add(1, Ordering::SeqCst);
+            self.0.zeroize();
+        }
+    }
+
+    #[test]
+    fn test_secret_arc_concurrent_reads_zeroize_once() {
+        let secret = SecretArc::new(Box::new(Counted([9; 16])));
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                let secret = secret.clone();
+                scope.spawn(move || assert_eq!(secret.expose_secret().0, [9; 16]));
+            }
+        });
+
+        assert_eq!(SecretArc::strong_count(&secret), 1);
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 0);
+
+        drop(secret);
+
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_secret_arc_shares_protected_secret() {
+        let secret = SecretArc::from(SecretBox::new_protected(Box::new([3u8; 8])));
+        let clone = secret.clone();
+
+        let first = secret.expose_secret();
+        let second = clone.expose_secret();
+
+        assert_eq!(*first, *second);
+        assert_eq!(format!("{clone:?}"), "SecretArc<[u8; 8]>([REDACTED])");
+    }
+
+    #[test]
+    fn test_secret_arc_try_new_protected() {
+        let secret = SecretArc::try_new_protected(Box::new([4u8; 8])).unwrap();
+
+        assert!(secret.0.is_protected());
+        assert_eq!(*secret.expose_secret(), [4; 8]);
+    }
+
+    #[test]
+    fn test_secret_arc_guard_moves_into_scoped_thread() {
+        fn assert_send<T: Send>(value: T) -> T {
+            value
+        }
+
+        let secret = SecretArc::new_protected(Box::new([5u8; 16]));
+        let guard = assert_send(secret.expose_secret());
+
+        let read = thread::scope(|scope| scope.spawn(move || *guard).join().unwrap());
+
+        // The guard was dropped on the scoped thread, concealing the pages again.
+        assert_eq!(read, [5; 16]);
+        assert_eq!(*secret.expose_secret(), [5; 16]);
+    }
+
+    #[test]
+    fn test_secret_arc_swap_concurrent_loads_and_store() {
+        let swap = SecretArcSwap::new(Box::new([0u64; 4]));
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        let secret = swap.load();
+                        let secret = secret.expose_secret();
+                        // Every word is stored together, so they always match.
+                        assert!(secret.iter().all(|&word| word == secret[0]));
+                    }
+                });
+            }
+
+            scope.spawn(|| {
+                for round in 1..=100 {
+                    swap.store(SecretBox::new(Box::new([round; 4])));
+                }
+            });
+        });
+
+        assert_eq!(*swap.load().expose_secret(), [100; 4]);
+    }
+
+    #[test]
+    fn test_secret_arc_swap_zeroizes_old_secret_after_readers() {
+        static ZEROIZED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Rotated(u8);
+
+        impl Zeroize for Rotated {
+            fn zeroize(&mut self) {
+                ZEROIZED.fetch_add(1, Ordering::SeqCst);
+                self.0.zeroize();
+            }
+        }
+
+        let swap = SecretArcSwap::new(Box::new(Rotated(1)));
+        let reader = swap.load();
+
+        swap.store(SecretBox::new(Box::new(Rotated(2))));
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 0);
+        assert_eq!(reader.expose_secret().0, 1);
+
+        drop(reader);
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 1);
+        assert_eq!(swap.load().expose_secret().0, 2);
+    }
+
+    #[test]
+    fn test_secret_arc_swap_compare_and_swap() {
+        let swap = SecretArcSwap::new(Box::new(1u32));
+        let stale = swap.load();
+
+        let old = swap.compare_and_swap(&stale, SecretBox::new(Box::new(2))).unwrap();
+        assert_eq!(*old.expose_secret(), 1);
+
+        let rejected = swap.compare_and_swap(&stale, SecretBox::new(Box::new(3))).unwrap_err();
+        assert_eq!(*rejected.expose_secret(), 3);
+        assert_eq!(*swap.load().expose_secret(), 2);
+        assert_eq!(format!("{swap:?}"), "SecretArcSwap<u32>([REDACTED])");
+    }
+}
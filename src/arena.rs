@@ -0,0 +1,222 @@
+//! Arenas of locked memory carved up by secrets of any size.
+
+use crate::{
+    advise_locked, lock,
+    page::{self, Pages},
+    release_locked, LockError,
+};
+use alloc::sync::Arc;
+use core::{
+    fmt::{self, Debug},
+    slice,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use zeroize::Zeroize;
+
+/// Fixed-size region of locked memory that secrets of any size are carved out of, see
+/// [`SecretBox::new_in_arena`](crate::SecretBox::new_in_arena).
+///
+/// The region is locked with one `mlock` call when the arena is created and unlocked
+/// with one `munlock` call once the arena and every secret in it are dropped, and the
+/// secrets sit next to each other in memory. Unlike a [`SecretPool`](crate::SecretPool)
+/// secrets don't need to fit a slot size: each one takes the next bytes of the region,
+/// aligned for its type. A secret is zeroized when it is dropped, but its bytes are not
+/// handed out again, so size the arena for every secret it will ever hold.
+///
+/// Cloning an arena only shares the same region.
+///
+/// # Examples
+///
+/// ```
+/// use shush::{ExposeSecret, SecretArena, SecretBox};
+///
+/// let arena = SecretArena::new(4096);
+///
+/// let signing_key = SecretBox::new_in_arena(Box::new([1u8; 32]), &arena);
+/// let mac_key = SecretBox::new_in_arena(Box::new([2u8; 64]), &arena);
+///
+/// assert_eq!(arena.remaining(), arena.capacity() - 96);
+/// assert_eq!((signing_key.expose_secret()[0], mac_key.expose_secret()[0]), (1, 2));
+/// ```
+#[derive(Clone)]
+pub struct SecretArena(Arc<Region>);
+
+struct Region {
+    pages: Pages,
+    /// Offset of the first byte not handed out yet.
+    next: AtomicUsize,
+}
+
+impl SecretArena {
+    /// Lock a region of at least `size` bytes, rounded up to whole pages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region cannot be locked, see [`Self::try_new`] for a fallible
+    /// version.
+    pub fn new(size: usize) -> Self {
+        Self::try_new(size).unwrap_or_else(lock::failed)
+    }
+
+    /// Same as [`Self::new`], but returns an error if the region cannot be locked.
+    pub fn try_new(size: usize) -> Result<Self, LockError> {
+        let pages = Pages::alloc(size);
+
+        unsafe {
+            lock::lock(pages.as_ptr(), pages.len())?;
+            advise_locked(pages.as_ptr(), pages.len());
+        }
+
+        Ok(Self(Arc::new(Region {
+            pages,
+            next: AtomicUsize::new(0),
+        })))
+    }
+
+    /// Size in bytes of the whole region.
+    pub fn capacity(&self) -> usize {
+        self.0.pages.len()
+    }
+
+    /// Number of bytes not handed out yet, some of which may go to padding.
+    pub fn remaining(&self) -> usize {
+        self.capacity() - self.0.next.load(Ordering::Relaxed)
+    }
+
+    /// Carve out the next `size` bytes aligned to `align`, if they fit.
+    pub(crate) fn claim(&self, size: usize, align: usize) -> Option<ArenaSlot> {
+        if align > page::page_size() {
+            return None;
+        }
+
+        let mut offset = 0;
+
+        // The region starts on a page boundary, so aligning offsets aligns pointers.
+        self.0
+            .next
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                offset = next.checked_next_multiple_of(align)?;
+                offset.checked_add(size).filter(|&end| end <= self.capacity())
+            })
+            .ok()?;
+
+        Some(ArenaSlot {
+            arena: self.clone(),
+            offset,
+            len: size,
+        })
+    }
+}
+
+impl Debug for SecretArena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretArena")
+            .field("capacity", &self.capacity())
+            .field("remaining", &self.remaining())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        // The pages are freed once they are dropped right after.
+        unsafe { release_locked(self.pages.as_ptr(), self.pages.len()) };
+    }
+}
+
+/// Bytes of an arena holding one secret, zeroized on drop.
+pub(crate) struct ArenaSlot {
+    pub(crate) arena: SecretArena,
+    offset: usize,
+    len: usize,
+}
+
+impl ArenaSlot {
+    /// Start of the slot.
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        unsafe { self.arena.0.pages.as_ptr().add(self.offset) }
+    }
+}
+
+impl Drop for ArenaSlot {
+    fn drop(&mut self) {
+        // Wipe whatever `S` left behind, e.g. the pointer of a `Vec`.
+        unsafe { slice::from_raw_parts_mut(self.as_ptr(), self.len) }.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExposeSecret, SecretBox};
+    use core::cell::Cell;
+
+    #[test]
+    fn test_arena_secrets_are_isolated() {
+        let arena = SecretArena::new(256);
+        let calls = lock::CALLS.with(Cell::get);
+
+        let mut a = SecretBox::new_in_arena(Box::new([1u8; 3]), &arena);
+        let b = SecretBox::new_in_arena(Box::new(2u64), &arena);
+        let c = SecretBox::new_in_arena(Box::new([3u8; 32]), &arena);
+
+        // Secrets follow each other, `b` is padded to its alignment.
+        let start = arena.0.pages.as_ptr();
+        let offsets = [&a.slot, &b.slot, &c.slot]
+            .map(|slot| slot.as_ref().unwrap().as_ptr() as usize - start as usize);
+        assert_eq!(offsets, [0, 8, 16]);
+        assert_eq!(arena.remaining(), arena.capacity() - 48);
+
+        a.expose_secret_mut().fill(0xff);
+
+        assert_eq!(*b.expose_secret(), 2);
+        assert_eq!(*c.expose_secret(), [3; 32]);
+        assert_eq!(lock::CALLS.with(Cell::get), calls);
+        assert_eq!(c.is_locked(), lock::ENABLED);
+    }
+
+    #[test]
+    fn test_arena_slot_zeroized_on_drop() {
+        let arena = SecretArena::new(64);
+
+        let secret = SecretBox::new_in_arena(Box::new(b"hunter2".to_vec()), &arena);
+        let neighbour = SecretBox::new_in_arena(Box::new([8u8; 16]), &arena);
+        let slot_ptr = secret.slot.as_ref().unwrap().as_ptr();
+        drop(secret);
+
+        // The region outlives the secret, so its bytes can still be read.
+        let slot = unsafe { slice::from_raw_parts(slot_ptr, size_of::<Vec<u8>>()) };
+        assert!(slot.iter().all(|&byte| byte == 0));
+        assert_eq!(*neighbour.expose_secret(), [8; 16]);
+    }
+
+    #[test]
+    fn test_full_arena_falls_back_to_pages() {
+        let arena = SecretArena::new(1);
+        let capacity = arena.capacity();
+        assert_eq!(capacity, page::page_size());
+
+        let fills: Vec<_> = (0..capacity / 512)
+            .map(|_| SecretBox::new_in_arena(Box::new([1u8; 512]), &arena))
+            .collect();
+        let overflow = SecretBox::new_in_arena(Box::new(2u8), &arena);
+
+        assert!(fills.iter().all(|secret| secret.slot.is_some()));
+        assert_eq!(arena.remaining(), 0);
+        assert!(overflow.slot.is_none() && overflow.protection.is_some());
+        assert_eq!(*overflow.expose_secret(), 2);
+    }
+
+    #[test]
+    fn test_secret_outlives_arena_handle() {
+        let arena = SecretArena::new(64);
+        let secret = SecretBox::new_in_arena(Box::new([9u8; 32]), &arena);
+        let clone = secret.clone();
+
+        drop(arena);
+
+        assert!(clone.slot.is_some());
+        assert_eq!(*secret.expose_secret(), [9; 32]);
+        assert_eq!(*clone.expose_secret(), [9; 32]);
+    }
+}
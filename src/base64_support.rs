@@ -0,0 +1,97 @@
+//! Base64 encoding and decoding of secrets, backed by the [`base64`] crate.
+//!
+//! Both directions use the standard alphabet with padding, and work on locked buffers
+//! directly so no plaintext intermediate is left behind.
+
+use crate::{heap::string_buffer, lock, ExposeSecret, SecretBox, SecretString};
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use base64::{engine::general_purpose::STANDARD, DecodeError, DecodeSliceError, Engine};
+
+impl SecretBox<Vec<u8>> {
+    /// Decode a base64 string, e.g. a key read from an environment variable, into a new
+    /// secret.
+    ///
+    /// The buffer is sized from the length of `s` and locked before decoding, then
+    /// truncated to the decoded bytes. On error the bytes decoded so far are zeroized.
+    /// `s` itself is left to the caller to zeroize.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let key = SecretBox::from_base64("3q2+7w==").unwrap();
+    ///
+    /// assert_eq!(*key.expose_secret(), [0xde, 0xad, 0xbe, 0xef]);
+    /// ```
+    pub fn from_base64(s: &str) -> Result<Self, DecodeError> {
+        let mut secret =
+            Self::try_zeroed(base64::decoded_len_estimate(s.len())).unwrap_or_else(lock::failed);
+
+        let len = match STANDARD.decode_slice(s, &mut secret.expose_secret_mut()) {
+            Ok(len) => len,
+            Err(DecodeSliceError::DecodeError(err)) => return Err(err),
+            Err(DecodeSliceError::OutputSliceTooSmall) => {
+                unreachable!("buffer sized from the decoded length estimate")
+            }
+        };
+        secret.expose_secret_mut().truncate(len);
+
+        Ok(secret)
+    }
+
+    /// Encode the secret as base64 into a new locked string.
+    ///
+    /// The string is allocated and locked before encoding, so the encoded secret never
+    /// lives in a plain `String`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the string cannot be locked.
+    pub fn expose_base64(&self) -> SecretString {
+        let bytes = self.expose_secret();
+        let len = base64::encoded_len(bytes.len(), true).expect("base64 length overflows usize");
+
+        // Zeroes are valid UTF-8, and base64 only writes ASCII over them.
+        let zeroed = String::from_utf8(vec![0; len]).expect("zeroes are valid UTF-8");
+        let mut encoded = SecretBox::new(Box::new(zeroed));
+        unsafe { encoded.lock_heap(string_buffer) }.unwrap_or_else(lock::failed);
+
+        STANDARD
+            .encode_slice(&*bytes, unsafe { encoded.expose_secret_mut().as_bytes_mut() })
+            .expect("buffer sized from the encoded length");
+
+        encoded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_round_trip() {
+        let key: Vec<u8> = (0..32).collect();
+        let secret = SecretBox::new(Box::new(key.clone()));
+
+        let encoded = secret.expose_base64();
+        assert_eq!(&*encoded.expose_str(), "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=");
+        assert!(encoded.locked_heap.is_some());
+
+        let decoded = SecretBox::from_base64(&encoded.expose_str()).unwrap();
+        assert_eq!(*decoded.expose_secret(), key);
+        assert!(decoded.locked_heap.is_some());
+    }
+
+    #[test]
+    fn test_from_base64_invalid() {
+        assert!(matches!(
+            SecretBox::from_base64("not base64!"),
+            Err(DecodeError::InvalidByte(3, b' '))
+        ));
+    }
+}
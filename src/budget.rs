@@ -0,0 +1,169 @@
+//! How much memory the process may still lock, to check before creating many secrets.
+
+use std::io;
+
+/// Limits on the memory the process may lock and how much of it is locked, see
+/// [`memlock_budget`].
+///
+/// Every secret takes at least a whole page of locked memory, so a service creating
+/// many of them can check the remaining budget first and fail gracefully instead of
+/// running into a [`LockError`](crate::LockError) halfway through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemlockBudget {
+    soft: Option<u64>,
+    hard: Option<u64>,
+    locked: Option<u64>,
+}
+
+impl MemlockBudget {
+    /// Soft limit in bytes, the one locking runs into, or `None` if unlimited.
+    pub fn soft_limit(&self) -> Option<u64> {
+        self.soft
+    }
+
+    /// Hard limit in bytes, up to which the soft limit can be raised, or `None` if
+    /// unlimited.
+    pub fn hard_limit(&self) -> Option<u64> {
+        self.hard
+    }
+
+    /// Bytes the process has locked, or `None` where the platform doesn't report it.
+    ///
+    /// Only Linux reports it, from `VmLck` in `/proc/self/status`.
+    pub fn locked(&self) -> Option<u64> {
+        self.locked
+    }
+
+    /// Bytes that can still be locked under the soft limit, or `None` if the limit is
+    /// unlimited or the locked bytes aren't reported.
+    pub fn remaining(&self) -> Option<u64> {
+        Some(self.soft?.saturating_sub(self.locked?))
+    }
+}
+
+/// Query the `RLIMIT_MEMLOCK` limits of the process and how much memory it has locked.
+///
+/// Processes with `CAP_IPC_LOCK` on Linux can lock memory past the limits.
+///
+/// # Errors
+///
+/// Returns an error if the limits cannot be read, and one of kind
+/// [`io::ErrorKind::Unsupported`] on platforms without `RLIMIT_MEMLOCK` such as Windows.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(unix)]
+/// # {
+/// let budget = shush::memlock_budget().unwrap();
+///
+/// if budget.remaining().is_some_and(|remaining| remaining < 64 << 10) {
+///     eprintln!("not enough lockable memory left for the key cache");
+/// }
+/// # }
+/// ```
+pub fn memlock_budget() -> io::Result<MemlockBudget> {
+    imp::memlock_budget()
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::MemlockBudget;
+    use std::io;
+
+    pub(super) fn memlock_budget() -> io::Result<MemlockBudget> {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+
+        if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // `rlim_t` is narrower than `u64` on some targets.
+        #[allow(clippy::unnecessary_cast)]
+        let bytes = |limit: libc::rlim_t| (limit != libc::RLIM_INFINITY).then_some(limit as u64);
+
+        Ok(MemlockBudget {
+            soft: bytes(limit.rlim_cur),
+            hard: bytes(limit.rlim_max),
+            locked: locked(),
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn locked() -> Option<u64> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        // e.g. `VmLck:	      16 kB`
+        let kb = status.lines().find_map(|line| line.strip_prefix("VmLck:"))?;
+
+        kb.trim().strip_suffix("kB")?.trim().parse::<u64>().ok().map(|kb| kb << 10)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn locked() -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use super::MemlockBudget;
+    use std::io;
+
+    pub(super) fn memlock_budget() -> io::Result<MemlockBudget> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "RLIMIT_MEMLOCK is not available on this platform",
+        ))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memlock_budget_limits() {
+        let budget = memlock_budget().unwrap();
+
+        match (budget.soft_limit(), budget.hard_limit()) {
+            (Some(soft), Some(hard)) => assert!(soft <= hard),
+            (Some(_), None) | (None, None) => {}
+            (None, Some(_)) => panic!("unlimited soft limit under a hard limit"),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_memlock_budget_counts_locked_secrets() {
+        use crate::{lock, SecretBox};
+
+        let secret = SecretBox::new(Box::new([0u8; 16 << 10]));
+        let budget = memlock_budget().unwrap();
+        let locked = budget.locked().unwrap();
+
+        if lock::ENABLED {
+            assert!(locked >= 16 << 10);
+        }
+        if let Some(soft) = budget.soft_limit() {
+            assert_eq!(budget.remaining(), Some(soft.saturating_sub(locked)));
+        }
+
+        drop(secret);
+    }
+
+    #[test]
+    fn test_memlock_budget_remaining() {
+        let budget = MemlockBudget {
+            soft: Some(64 << 10),
+            hard: None,
+            locked: Some(16 << 10),
+        };
+
+        assert_eq!(budget.remaining(), Some(48 << 10));
+        assert_eq!(MemlockBudget { soft: None, ..budget }.remaining(), None);
+        assert_eq!(MemlockBudget { locked: None, ..budget }.remaining(), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Building a secret incrementally in locked memory.
+
+use crate::{lock, LockError, SecretBox};
+use alloc::vec::Vec;
+use core::fmt::{self, Debug};
+#[cfg(feature = "std")]
+use std::io;
+
+/// Growable secret byte buffer whose bytes only ever live in locked memory, e.g. to
+/// concatenate key material from several sources.
+///
+/// When the buffer runs out of capacity the bytes are moved to a bigger locked buffer
+/// and the old one is zeroized before it is freed. It implements [`Extend`], and with
+/// the `std` feature [`std::io::Write`]. Finalize it with [`Self::into_secret`].
+///
+/// # Examples
+///
+/// ```
+/// use shush::{ExposeSecret, SecretBuffer};
+///
+/// let mut buffer = SecretBuffer::new();
+/// buffer.extend_from_slice(b"salt:");
+/// buffer.extend_from_slice(b"pepper");
+///
+/// let secret = buffer.into_secret();
+/// assert_eq!(*secret.expose_secret(), b"salt:pepper");
+/// ```
+pub struct SecretBuffer(SecretBox<Vec<u8>>);
+
+impl SecretBuffer {
+    /// Create an empty buffer. Like every [`SecretBox`] it allocates and locks a page
+    /// right away, the bytes appended later get a locked buffer of their own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the buffer cannot be locked.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Create an empty buffer with a locked capacity of at least `capacity` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the buffer cannot be locked.
+    pub fn with_capacity(capacity: usize) -> Self {
+        SecretBox::try_with_capacity(capacity)
+            .map(Self)
+            .unwrap_or_else(lock::failed)
+    }
+
+    /// Append `bytes` to the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer has to grow and the new buffer cannot be locked, see
+    /// [`Self::try_extend_from_slice`] for a fallible version.
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.try_extend_from_slice(bytes).unwrap_or_else(lock::failed)
+    }
+
+    /// Append `bytes` to the buffer, returning an error if it has to grow and the new
+    /// buffer cannot be locked. The buffer is left unchanged then.
+    pub fn try_extend_from_slice(&mut self, bytes: &[u8]) -> Result<(), LockError> {
+        self.0.try_reserve_locked(bytes.len())?;
+        self.0.with_secret_mut(|vec| vec.extend_from_slice(bytes));
+
+        Ok(())
+    }
+
+    /// Number of bytes in the buffer, without exposing it.
+    pub fn len(&self) -> usize {
+        self.0.secret_len(Vec::len)
+    }
+
+    /// Whether the buffer is empty, without exposing it.
+    pub fn is_empty(&self) -> bool {
+        self.0.secret_len(Vec::len) == 0
+    }
+
+    /// Finish building the secret, keeping its locked buffer.
+    pub fn into_secret(self) -> SecretBox<Vec<u8>> {
+        self.0
+    }
+}
+
+/// Append bytes from an iterator, growing the locked buffer as needed.
+///
+/// # Panics
+///
+/// Panics if the buffer has to grow and the new buffer cannot be locked.
+impl Extend<u8> for SecretBuffer {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.0.reserve_locked(iter.size_hint().0);
+
+        for byte in iter {
+            self.0.reserve_locked(1);
+            self.0.with_secret_mut(|vec| vec.push(byte));
+        }
+    }
+}
+
+impl<'a> Extend<&'a u8> for SecretBuffer {
+    fn extend<I: IntoIterator<Item = &'a u8>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
+impl Default for SecretBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for SecretBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBuffer({})", self.0.debug_label)
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Write for SecretBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.try_extend_from_slice(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{heap::assert_heap_locked, ExposeSecret};
+    use std::io::Write;
+
+    #[test]
+    fn test_secret_buffer_write_chunks() {
+        let mut buffer = SecretBuffer::new();
+
+        for chunk in [&b"key"[..], b"-", &[7; 100], b"-material"] {
+            buffer.write_all(chunk).unwrap();
+        }
+        assert_eq!(buffer.len(), 113);
+
+        let mut secret = buffer.into_secret();
+        let mut expected = b"key-".to_vec();
+        expected.extend_from_slice(&[7; 100]);
+        expected.extend_from_slice(b"-material");
+        assert_eq!(*secret.expose_secret(), expected);
+
+        assert_heap_locked(&mut secret);
+    }
+
+    #[test]
+    fn test_secret_buffer_extend() {
+        let mut buffer = SecretBuffer::new();
+
+        buffer.extend((0..200u32).map(|i| i as u8).filter(|byte| byte % 2 == 0));
+        buffer.extend(&[0xff, 0xfe]);
+
+        let mut secret = buffer.into_secret();
+        let mut expected: Vec<u8> = (0..200u8).step_by(2).collect();
+        expected.extend_from_slice(&[0xff, 0xfe]);
+        assert_eq!(*secret.expose_secret(), expected);
+
+        assert_heap_locked(&mut secret);
+    }
+
+    #[test]
+    fn test_secret_buffer_debug() {
+        let mut buffer = SecretBuffer::with_capacity(4);
+        buffer.extend_from_slice(b"hunter2");
+
+        assert_eq!(format!("{buffer:?}"), "SecretBuffer([REDACTED])");
+    }
+}
@@ -0,0 +1,205 @@
+//! Choosing the protections of a secret one by one.
+
+use crate::{page::Pages, protect::Protection, LockError, SecretBox};
+use alloc::boxed::Box;
+use core::mem::size_of;
+use zeroize::Zeroize;
+
+/// Builder for a [`SecretBox`] with the protections it needs, instead of picking
+/// between [`SecretBox::new_protected`], [`SecretBox::new_guarded`] and so on.
+///
+/// Every secret is locked, lives in pages of its own and gets the `madvise` hints of
+/// the platform. On top of that each protection is opted into separately and they can
+/// be combined, e.g. a protected secret between guard pages. The builder only holds the
+/// policy, so one can configure many secrets.
+///
+/// # Examples
+///
+/// ```
+/// use shush::{ExposeSecret, SecretBoxBuilder};
+///
+/// let policy = SecretBoxBuilder::new().protected(true).guarded(true);
+///
+/// let key = policy.build(Box::new([7u8; 32])).unwrap();
+///
+/// assert!(key.is_protected() && key.is_guarded());
+/// assert_eq!(*key.expose_secret(), [7; 32]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SecretBoxBuilder {
+    protected: bool,
+    guarded: bool,
+    on_fault: bool,
+    hugepages: bool,
+    debug_label: Option<&'static str>,
+}
+
+impl SecretBoxBuilder {
+    /// Create a builder for secrets locked like with [`SecretBox::new`], without any
+    /// other protection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the pages of the secret are inaccessible while no guard exposes it, see
+    /// [`SecretBox::new_protected`].
+    pub fn protected(mut self, protected: bool) -> Self {
+        self.protected = protected;
+        self
+    }
+
+    /// Whether the secret sits between two inaccessible guard pages, see
+    /// [`SecretBox::new_guarded`].
+    pub fn guarded(mut self, guarded: bool) -> Self {
+        self.guarded = guarded;
+        self
+    }
+
+    /// Whether the pages of the secret are only locked once they are touched, see
+    /// [`SecretBox::new_on_fault`].
+    pub fn lock_on_fault(mut self, on_fault: bool) -> Self {
+        self.on_fault = on_fault;
+        self
+    }
+
+    /// Whether the secret is backed by huge pages where available, see
+    /// [`SecretBox::new_hugepages`]. Guard pages are normal pages, so a guarded secret
+    /// ignores this.
+    pub fn hugepages(mut self, hugepages: bool) -> Self {
+        self.hugepages = hugepages;
+        self
+    }
+
+    /// Print `label` in `Debug` instead of the default redaction marker, see
+    /// [`SecretBox::with_debug_label`].
+    pub fn debug_label(mut self, label: &'static str) -> Self {
+        self.debug_label = Some(label);
+        self
+    }
+
+    /// Move `boxed_secret` into a secret with the chosen protections, returning an
+    /// error if its memory cannot be locked.
+    ///
+    /// On failure the secret is zeroized before the error is returned.
+    pub fn build<S: Zeroize>(&self, boxed_secret: Box<S>) -> Result<SecretBox<S>, LockError> {
+        let (pages, offset) = match (self.guarded, self.hugepages) {
+            (true, _) => {
+                let pages = Pages::alloc_guarded(size_of::<S>());
+                // The size of a type is a multiple of its alignment, so this stays
+                // aligned.
+                let offset = pages.len() - size_of::<S>();
+
+                (pages, offset)
+            }
+            (false, true) => {
+                let pages = Pages::alloc_huge(size_of::<S>())
+                    .unwrap_or_else(|| Pages::alloc(size_of::<S>()));
+
+                (pages, 0)
+            }
+            (false, false) => (Pages::alloc(size_of::<S>()), 0),
+        };
+        let protection = match self.protected {
+            true => Protection::new,
+            false => Protection::unprotected,
+        };
+
+        let secret =
+            SecretBox::try_new_locked_with(boxed_secret, pages, offset, protection, self.on_fault)?;
+
+        Ok(match self.debug_label {
+            Some(label) => secret.with_debug_label(label),
+            None => secret,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lock, ExposeSecret};
+
+    fn protections<S: Zeroize>(secret: &SecretBox<S>) -> (bool, bool, bool) {
+        (secret.is_locked(), secret.is_protected(), secret.is_guarded())
+    }
+
+    #[test]
+    fn test_builder_default_matches_new() {
+        let secret = SecretBoxBuilder::new().build(Box::new(1u64)).unwrap();
+
+        assert_eq!(protections(&secret), protections(&SecretBox::new(Box::new(1u64))));
+        assert_eq!(protections(&secret), (lock::ENABLED, false, false));
+        assert_eq!(*secret.expose_secret(), 1);
+    }
+
+    #[test]
+    fn test_builder_combinations() {
+        for protected in [false, true] {
+            for guarded in [false, true] {
+                for on_fault in [false, true] {
+                    let builder = SecretBoxBuilder::new()
+                        .protected(protected)
+                        .guarded(guarded)
+                        .lock_on_fault(on_fault);
+
+                    let mut secret = builder.build(Box::new([3u8; 24])).unwrap();
+                    secret.expose_secret_mut()[0] = 4;
+
+                    assert_eq!(protections(&secret), (lock::ENABLED, protected, guarded));
+                    assert_eq!(secret.expose_secret()[..2], [4, 3]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_builder_guarded_secret_ends_on_guard_page() {
+        let secret = SecretBoxBuilder::new()
+            .protected(true)
+            .guarded(true)
+            .build(Box::new([0u8; 24]))
+            .unwrap();
+
+        let end = secret.with_secret(|secret| secret.as_ptr_range().end) as usize;
+
+        assert_eq!(end % crate::page::page_size(), 0);
+    }
+
+    #[test]
+    fn test_builder_debug_label() {
+        let secret = SecretBoxBuilder::new()
+            .debug_label("api token")
+            .build(Box::new(1u8))
+            .unwrap();
+
+        assert!(format!("{secret:?}").ends_with("(api token)"));
+    }
+
+    #[test]
+    fn test_clone_keeps_built_protections() {
+        let secret = SecretBoxBuilder::new()
+            .protected(true)
+            .guarded(true)
+            .build(Box::new([5u8; 8]))
+            .unwrap();
+
+        let clone = secret.clone();
+
+        assert_eq!(protections(&clone), protections(&secret));
+    }
+
+    #[test]
+    fn test_clone_keeps_lock_on_fault_and_hugepages() {
+        let secret = SecretBoxBuilder::new()
+            .lock_on_fault(true)
+            .hugepages(true)
+            .build(Box::new([6u8; 8]))
+            .unwrap();
+
+        let clone = secret.try_clone().unwrap();
+
+        assert!(clone.on_fault);
+        assert_eq!(clone.is_huge(), secret.is_huge());
+        assert_eq!(*clone.expose_secret(), [6; 8]);
+    }
+}
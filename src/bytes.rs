@@ -0,0 +1,123 @@
+//! Secret byte slices whose length is only known at runtime.
+
+use crate::{
+    heap::boxed_slice_buffer, lock, ExposeSecret, LockError, SecretBox, SecretGuard, SecretGuardMut,
+};
+use alloc::{boxed::Box, vec};
+
+/// Secret bytes of a fixed length chosen at runtime, zeroized on drop and redacted in
+/// `Debug`.
+///
+/// Unlike a `SecretBox<Vec<u8>>` there is no spare capacity: the buffer holds exactly
+/// `len` initialized bytes, and exactly that buffer is locked.
+///
+/// # Examples
+///
+/// ```
+/// use shush::SecretBytes;
+///
+/// let mut key = SecretBytes::new_zeroed(32);
+/// key.expose_slice_mut().fill(7);
+///
+/// assert_eq!(key.len(), 32);
+/// assert_eq!(key.expose_slice()[31], 7);
+/// ```
+pub type SecretBytes = SecretBox<Box<[u8]>>;
+
+impl SecretBytes {
+    /// Create `len` zeroed secret bytes, locking them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see [`Self::try_new_zeroed`]
+    /// for a fallible version.
+    pub fn new_zeroed(len: usize) -> Self {
+        Self::try_new_zeroed(len).unwrap_or_else(lock::failed)
+    }
+
+    /// Create `len` zeroed secret bytes, returning an error if they cannot be locked.
+    pub fn try_new_zeroed(len: usize) -> Result<Self, LockError> {
+        let mut secret = Self::try_new(Box::new(vec![0; len].into_boxed_slice()))?;
+        unsafe { secret.lock_heap(boxed_slice_buffer)? };
+
+        Ok(secret)
+    }
+
+    /// Expose the secret as a byte slice.
+    pub fn expose_slice(&self) -> SecretGuard<'_, [u8]> {
+        SecretGuard::map(self.expose_secret(), |bytes| &**bytes)
+    }
+
+    /// Expose the secret as a mutable byte slice, e.g. to fill it from a key exchange.
+    pub fn expose_slice_mut(&mut self) -> SecretGuardMut<'_, [u8]> {
+        SecretGuardMut::map(self.expose_secret_mut(), |bytes| &mut **bytes)
+    }
+
+    /// Number of bytes in the secret, without exposing it.
+    pub fn len(&self) -> usize {
+        self.secret_len(|bytes| bytes.len())
+    }
+
+    /// Whether the secret holds no bytes, without exposing it.
+    pub fn is_empty(&self) -> bool {
+        self.secret_len(|bytes| bytes.len()) == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heap::assert_heap_locked;
+
+    #[test]
+    fn test_secret_bytes_zeroed_locks_exactly_its_bytes() {
+        let mut secret = SecretBytes::new_zeroed(100);
+
+        assert_eq!(secret.len(), 100);
+        assert!(!secret.is_empty());
+        assert_eq!(*secret.expose_slice(), [0; 100]);
+
+        let (_, len) = assert_heap_locked(&mut secret);
+        assert_eq!(len, 100);
+    }
+
+    #[test]
+    fn test_secret_bytes_mutation() {
+        let mut secret = SecretBytes::new_zeroed(4);
+
+        secret.expose_slice_mut().copy_from_slice(b"key!");
+        secret.expose_slice_mut()[3] = b'?';
+
+        assert_eq!(&*secret.expose_slice(), b"key?");
+    }
+
+    #[test]
+    fn test_secret_bytes_protected_mutation() {
+        let mut secret = SecretBytes::new_protected(Box::new(vec![1; 8].into_boxed_slice()));
+
+        secret.expose_slice_mut().fill(2);
+
+        assert_eq!(*secret.expose_slice(), [2; 8]);
+    }
+
+    #[test]
+    fn test_secret_bytes_zeroized() {
+        let mut secret = SecretBytes::new_zeroed(64);
+        secret.expose_slice_mut().fill(0xaa);
+
+        // Destroying wipes the secret the same way dropping it does.
+        secret.destroy();
+
+        let bytes = unsafe { secret.inner_secret.as_ref() };
+        assert_eq!(bytes.len(), 64);
+        assert!(bytes.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_secret_bytes_empty() {
+        let secret = SecretBytes::new_zeroed(0);
+
+        assert!(secret.is_empty());
+        assert!(secret.expose_slice().is_empty());
+    }
+}
@@ -0,0 +1,283 @@
+//! Secrets that can be updated behind a shared reference.
+
+use crate::{trace, ExposeSecret, LockError, SecretBox, SecretGuard, SecretGuardMut};
+use alloc::boxed::Box;
+use core::{
+    any,
+    fmt::{self, Debug},
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use zeroize::Zeroize;
+
+/// Borrow count of a cell that is mutably borrowed.
+const WRITING: usize = usize::MAX;
+
+/// Secret with `RefCell`-like interior mutability, e.g. a token rotated through an
+/// `Arc`.
+///
+/// [`Self::borrow`] and [`Self::borrow_mut`] return guards and check at runtime that a
+/// mutable borrow is never alive along with any other borrow, panicking otherwise. Use
+/// [`Self::try_borrow`] and [`Self::try_borrow_mut`] to get `None` instead. The borrow
+/// count is atomic, so the cell can be shared between threads, but a conflicting borrow
+/// never waits for the other one to end.
+///
+/// The secret stays in its locked allocation and is zeroized when the cell is dropped,
+/// like a [`SecretBox`]. Assigning a new value through a mutable borrow drops the old
+/// one without zeroizing it, overwrite it in place instead.
+///
+/// # Examples
+///
+/// ```
+/// use shush::SecretCell;
+/// use std::sync::Arc;
+///
+/// let token = Arc::new(SecretCell::new(Box::new(*b"token-1")));
+/// let shared = Arc::clone(&token);
+///
+/// shared.borrow_mut().copy_from_slice(b"token-2");
+///
+/// assert_eq!(&*token.borrow(), b"token-2");
+/// ```
+pub struct SecretCell<S: Zeroize> {
+    secret: SecretBox<S>,
+    /// Number of shared borrows alive, or [`WRITING`].
+    borrows: AtomicUsize,
+}
+
+// Mutable borrows hand out `&mut S` through a shared reference, possibly on another
+// thread than the one that created the cell.
+unsafe impl<S: Zeroize + Send + Sync> Sync for SecretCell<S> {}
+
+impl<S: Zeroize> SecretCell<S> {
+    /// Create a secret cell using a pre-boxed value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see [`Self::try_new`]
+    /// for a fallible version.
+    pub fn new(boxed_secret: Box<S>) -> Self {
+        Self::from(SecretBox::new(boxed_secret))
+    }
+
+    /// Create a secret cell using a pre-boxed value, returning an error if its memory
+    /// cannot be locked.
+    pub fn try_new(boxed_secret: Box<S>) -> Result<Self, LockError> {
+        SecretBox::try_new(boxed_secret).map(Self::from)
+    }
+
+    /// Borrow the secret as non-mutable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret is mutably borrowed.
+    pub fn borrow(&self) -> SecretRef<'_, S> {
+        self.try_borrow().expect("secret already mutably borrowed")
+    }
+
+    /// Borrow the secret as non-mutable, or return `None` if it is mutably borrowed.
+    pub fn try_borrow(&self) -> Option<SecretRef<'_, S>> {
+        self.borrows
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |borrows| {
+                borrows.checked_add(1).filter(|&next| next != WRITING)
+            })
+            .ok()?;
+
+        Some(SecretRef {
+            guard: ManuallyDrop::new(self.secret.expose_secret()),
+            borrows: &self.borrows,
+        })
+    }
+
+    /// Borrow the secret as mutable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret is borrowed.
+    pub fn borrow_mut(&self) -> SecretRefMut<'_, S> {
+        self.try_borrow_mut().expect("secret already borrowed")
+    }
+
+    /// Borrow the secret as mutable, or return `None` if it is borrowed.
+    pub fn try_borrow_mut(&self) -> Option<SecretRefMut<'_, S>> {
+        self.secret.assert_alive();
+        self.borrows
+            .compare_exchange(0, WRITING, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        trace::exposed(&self.secret);
+
+        // The borrow count guarantees no other reference to the secret is alive.
+        let secret = unsafe { &mut *self.secret.inner_secret.as_ptr() };
+
+        Some(SecretRefMut {
+            guard: ManuallyDrop::new(SecretGuardMut::with_protection(
+                secret,
+                self.secret.protection.as_ref(),
+            )),
+            borrows: &self.borrows,
+        })
+    }
+
+    /// Take the secret out of the cell, keeping its locked allocation.
+    pub fn into_inner(self) -> SecretBox<S> {
+        self.secret
+    }
+}
+
+impl<S: Zeroize> From<SecretBox<S>> for SecretCell<S> {
+    /// Wrap an existing secret, keeping its locked allocation.
+    fn from(secret: SecretBox<S>) -> Self {
+        Self {
+            secret,
+            borrows: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<S: Zeroize> Debug for SecretCell<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretCell<{}>({})", any::type_name::<S>(), self.secret.debug_label)
+    }
+}
+
+/// Guard returned by [`SecretCell::borrow`].
+pub struct SecretRef<'a, S: Zeroize> {
+    guard: ManuallyDrop<SecretGuard<'a, S>>,
+    borrows: &'a AtomicUsize,
+}
+
+impl<S: Zeroize> Deref for SecretRef<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.guard
+    }
+}
+
+impl<S: Zeroize> Drop for SecretRef<'_, S> {
+    fn drop(&mut self) {
+        // Conceal the pages before a mutable borrow can expose them writable.
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        self.borrows.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Guard returned by [`SecretCell::borrow_mut`].
+pub struct SecretRefMut<'a, S: Zeroize> {
+    guard: ManuallyDrop<SecretGuardMut<'a, S>>,
+    borrows: &'a AtomicUsize,
+}
+
+impl<S: Zeroize> Deref for SecretRefMut<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.guard
+    }
+}
+
+impl<S: Zeroize> DerefMut for SecretRefMut<'_, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.guard
+    }
+}
+
+impl<S: Zeroize> Drop for SecretRefMut<'_, S> {
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        self.borrows.store(0, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::sync::Arc;
+    use core::sync::atomic::AtomicUsize;
+    use std::thread;
+
+    static ZEROIZED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted([u8; 16]);
+
+    impl Zeroize for Counted {
+        fn zeroize(&mut self) {
+            ZEROIZED.fetch_add(1, Ordering::SeqCst);
+            self.0.zeroize();
+        }
+    }
+
+    #[test]
+    fn test_secret_cell_borrow_conflicts() {
+        let cell = SecretCell::new(Box::new([1u8; 8]));
+
+        let first = cell.borrow();
+        let second = cell.borrow();
+        assert!(cell.try_borrow_mut().is_none());
+        assert_eq!(*first, *second);
+
+        drop((first, second));
+        let mut writer = cell.borrow_mut();
+        assert!(cell.try_borrow().is_none());
+        assert!(cell.try_borrow_mut().is_none());
+        writer[0] = 2;
+
+        drop(writer);
+        assert_eq!(cell.borrow()[0], 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "secret already mutably borrowed")]
+    fn test_secret_cell_borrow_while_mutably_borrowed_panics() {
+        let cell = SecretCell::new(Box::new(7u64));
+        let _writer = cell.borrow_mut();
+
+        cell.borrow();
+    }
+
+    #[test]
+    #[should_panic(expected = "secret already borrowed")]
+    fn test_secret_cell_borrow_mut_while_borrowed_panics() {
+        let cell = SecretCell::new(Box::new(7u64));
+        let _reader = cell.borrow();
+
+        cell.borrow_mut();
+    }
+
+    #[test]
+    #[should_panic(expected = "secret was destroyed")]
+    fn test_secret_cell_borrow_mut_of_destroyed_secret_panics() {
+        let mut secret = SecretBox::new(Box::new(7u64));
+        secret.destroy();
+
+        SecretCell::from(secret).borrow_mut();
+    }
+
+    #[test]
+    fn test_secret_cell_rotates_through_shared_reference() {
+        let token = Arc::new(SecretCell::from(SecretBox::new_protected(Box::new([0u8; 4]))));
+
+        for round in 1..=4u8 {
+            let shared = Arc::clone(&token);
+            thread::spawn(move || shared.borrow_mut().copy_from_slice(&[round; 4]))
+                .join()
+                .unwrap();
+
+            assert_eq!(*token.borrow(), [round; 4]);
+        }
+
+        assert_eq!(*token.borrow(), [4; 4]);
+    }
+
+    #[test]
+    fn test_secret_cell_zeroizes_on_drop() {
+        let cell = SecretCell::new(Box::new(Counted([9; 16])));
+        cell.borrow_mut().0[0] = 1;
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 0);
+
+        drop(cell);
+
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,515 @@
+//! Constant-time operations on secrets, backed by the [`subtle`] crate.
+//!
+//! Comparing byte secrets of different lengths can't hide that the lengths differ,
+//! since the result is "not equal" either way. What the byte comparisons below do
+//! hide is everything else about the secret: the time taken only depends on the
+//! length of the right-hand side, which the caller already knows, and a wrong length
+//! gives the same result as wrong bytes. The one exception is whether the secret is
+//! empty, which changes how it is read.
+
+use crate::{ExposeSecret, SecretBox};
+use alloc::{boxed::Box, vec::Vec};
+use core::cmp::Ordering;
+use subtle::{
+    Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater, ConstantTimeLess,
+};
+use zeroize::Zeroize;
+
+/// Compare two secrets without leaking timing information about their contents.
+///
+/// Prefer this over comparing the exposed values with `==`, which short-circuits on the
+/// first differing byte.
+impl<S> ConstantTimeEq for SecretBox<S>
+where
+    S: Zeroize + ConstantTimeEq,
+{
+    fn ct_eq(&self, other: &Self) -> Choice {
+        (*self.expose_secret()).ct_eq(&*other.expose_secret())
+    }
+}
+
+/// Compare two secrets with `==` in constant time, through [`ConstantTimeEq`].
+///
+/// Secrets whose type doesn't implement [`ConstantTimeEq`] deliberately can't be
+/// compared with `==`, so comparing them never falls back to a comparison that exits
+/// early. Compare such secrets with [`SecretBox::verify_with`] or, for bytes, with
+/// `ct_eq_slice`.
+///
+/// # Examples
+///
+/// ```
+/// use shush::SecretBox;
+///
+/// let pin = SecretBox::new(Box::new(1234u32));
+///
+/// assert!(pin == SecretBox::new(Box::new(1234u32)));
+/// assert!(pin != SecretBox::new(Box::new(4321u32)));
+/// ```
+impl<S> PartialEq for SecretBox<S>
+where
+    S: Zeroize + ConstantTimeEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl<S> Eq for SecretBox<S> where S: Zeroize + ConstantTimeEq {}
+
+/// Order two secret numbers, e.g. counters or nonces, without leaking timing
+/// information about their values.
+impl<S> ConstantTimeGreater for SecretBox<S>
+where
+    S: Zeroize + ConstantTimeGreater,
+{
+    fn ct_gt(&self, other: &Self) -> Choice {
+        (*self.expose_secret()).ct_gt(&*other.expose_secret())
+    }
+}
+
+impl<S> ConstantTimeLess for SecretBox<S> where S: Zeroize + ConstantTimeEq + ConstantTimeGreater {}
+
+impl<S> SecretBox<S>
+where
+    S: Zeroize + ConditionallySelectable,
+{
+    /// Select `a` if `choice` is 0 or `b` if it is 1 into a new secret, without leaking
+    /// which one was picked.
+    ///
+    /// The new secret is locked like `a`. Both secrets are read in full whatever the
+    /// choice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the new secret cannot be locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    /// use subtle::Choice;
+    ///
+    /// let a = SecretBox::new(Box::new(1u64));
+    /// let b = SecretBox::new(Box::new(2u64));
+    ///
+    /// let selected = SecretBox::conditional_select(&a, &b, Choice::from(1));
+    /// assert_eq!(*selected.expose_secret(), 2);
+    /// ```
+    pub fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut selected = a.new_like(Box::new(*a.expose_secret()));
+        selected.with_secret_mut(|secret| secret.conditional_assign(&*b.expose_secret(), choice));
+
+        selected
+    }
+}
+
+impl SecretBox<Vec<u8>> {
+    /// Compare the secret bytes against a plaintext, e.g. a submitted API key, in
+    /// constant time.
+    ///
+    /// The time taken only depends on the length of `other`, not on the length of the
+    /// secret. Secrets of a different length never compare equal.
+    pub fn ct_eq_slice(&self, other: &[u8]) -> Choice {
+        self.with_secret(|secret| ct_eq_bytes(secret, other))
+    }
+
+    /// Compare with another byte secret in constant time, even if their lengths differ.
+    ///
+    /// The time taken only depends on the length of `other`, so put the secret whose
+    /// length may leak on the right. Secrets of a different length never compare equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// let key = SecretBox::new(Box::new(b"api-key".to_vec()));
+    /// let prefix = SecretBox::new(Box::new(b"api".to_vec()));
+    ///
+    /// assert!(bool::from(key.ct_eq_secret(&key.clone())));
+    /// assert!(!bool::from(key.ct_eq_secret(&prefix)));
+    /// ```
+    pub fn ct_eq_secret(&self, other: &Self) -> Choice {
+        other.with_secret(|other| self.ct_eq_slice(other))
+    }
+
+    /// Compare with another byte secret lexicographically, like `<[u8]>::cmp`, without
+    /// exiting early on the first differing byte.
+    ///
+    /// For secrets of the same length the time taken only depends on that length, and
+    /// only the returned ordering is leaked. Secrets of different lengths are compared
+    /// over the length of the shorter one, which is then ordered first if they are
+    /// equal up to there, so their lengths are not hidden.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    /// use std::cmp::Ordering;
+    ///
+    /// let a = SecretBox::new(Box::new(vec![1u8, 2, 3]));
+    /// let b = SecretBox::new(Box::new(vec![1u8, 3, 0]));
+    ///
+    /// assert_eq!(a.ct_cmp(&b), Ordering::Less);
+    /// assert_eq!(b.ct_cmp(&a), Ordering::Greater);
+    /// assert_eq!(a.ct_cmp(&a.clone()), Ordering::Equal);
+    /// ```
+    pub fn ct_cmp(&self, other: &Self) -> Ordering {
+        let (greater, less, len) = self.with_secret(|secret| {
+            other.with_secret(|other| {
+                let (mut greater, mut less) = (Choice::from(0), Choice::from(0));
+
+                for (a, b) in secret.iter().zip(other.iter()) {
+                    // Only the first differing byte decides, later ones are still read.
+                    let undecided = !(greater | less);
+                    greater |= undecided & a.ct_gt(b);
+                    less |= undecided & b.ct_gt(a);
+                }
+
+                (greater, less, secret.len().cmp(&other.len()))
+            })
+        });
+
+        match (bool::from(greater), bool::from(less)) {
+            (true, _) => Ordering::Greater,
+            (_, true) => Ordering::Less,
+            _ => len,
+        }
+    }
+}
+
+impl<const N: usize> SecretBox<[u8; N]> {
+    /// Compare the secret bytes against a plaintext in constant time.
+    ///
+    /// The time taken only depends on the length of `other`.
+    pub fn ct_eq_slice(&self, other: &[u8]) -> Choice {
+        self.with_secret(|secret| ct_eq_bytes(secret, other))
+    }
+
+    /// Check a MAC or tag received with a message against the expected one held in the
+    /// secret, in constant time.
+    ///
+    /// Both sides have the same length, so the comparison leaks nothing but the result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// let expected = SecretBox::new(Box::new([0x5a; 32]));
+    ///
+    /// assert!(expected.verify_mac(&[0x5a; 32]));
+    /// assert!(!expected.verify_mac(&[0; 32]));
+    /// ```
+    pub fn verify_mac(&self, computed: &[u8; N]) -> bool {
+        self.with_secret(|secret| secret[..].ct_eq(&computed[..]).into())
+    }
+}
+
+impl<S> SecretBox<S>
+where
+    S: Zeroize + AsRef<[u8]>,
+{
+    /// Read byte `i` of the secret without leaking `i` through timing, e.g. a table
+    /// lookup at a secret-dependent index.
+    ///
+    /// Every byte is read and the one at `i` is selected with `subtle`, so this costs
+    /// O(n) in the length of the secret instead of a single read. An index past the
+    /// end returns 0 rather than panicking, which would leak it too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// let table = SecretBox::new(Box::new([10u8, 20, 30, 40]));
+    ///
+    /// assert_eq!(table.ct_get(2), 30);
+    /// assert_eq!(table.ct_get(4), 0);
+    /// ```
+    pub fn ct_get(&self, i: usize) -> u8 {
+        self.with_secret(|secret| {
+            let index = i as u64;
+
+            secret.as_ref().iter().enumerate().fold(0, |selected, (j, byte)| {
+                u8::conditional_select(&selected, byte, (j as u64).ct_eq(&index))
+            })
+        })
+    }
+}
+
+/// Compare `secret` with `other` without exiting early.
+///
+/// The loop always runs over `other`, whose length the caller already knows, wrapping
+/// around a shorter secret and stopping short of a longer one, so it compares exactly
+/// `other.len()` elements whatever the length of the secret. Only whether the secret
+/// is empty and whether the lengths match, through the result, are leaked.
+fn ct_eq_bytes<T: ConstantTimeEq + Copy + Default>(secret: &[T], other: &[T]) -> Choice {
+    let secret_len = secret.len() as u64;
+    let len_eq = secret_len.ct_eq(&(other.len() as u64));
+    let mut i = 0u64;
+
+    let bytes_eq = other.iter().fold(Choice::from(1), |eq, byte| {
+        let secret_byte = secret.get(i as usize).copied().unwrap_or_default();
+        // Wrap around by selecting rather than with `%`, a division may take a time
+        // that depends on the length of the secret.
+        let next = i + 1;
+        i = u64::conditional_select(&next, &0, next.ct_eq(&secret_len));
+
+        eq & secret_byte.ct_eq(byte)
+    });
+
+    len_eq & bytes_eq
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+
+    struct Token([u8; 16]);
+
+    impl Zeroize for Token {
+        fn zeroize(&mut self) {
+            self.0.zeroize()
+        }
+    }
+
+    impl ConstantTimeEq for Token {
+        fn ct_eq(&self, other: &Self) -> Choice {
+            self.0[..].ct_eq(&other.0[..])
+        }
+    }
+
+    #[test]
+    fn test_ct_eq_equal_secrets() {
+        let a = SecretBox::new(Box::new(Token([7; 16])));
+        let b = SecretBox::new(Box::new(Token([7; 16])));
+
+        assert!(bool::from(a.ct_eq(&b)));
+        assert!(!bool::from(a.ct_ne(&b)));
+    }
+
+    #[test]
+    fn test_ct_eq_unequal_secrets() {
+        let a = SecretBox::new(Box::new(Token([7; 16])));
+        let mut bytes = [7; 16];
+        bytes[15] = 8;
+        let b = SecretBox::new(Box::new(Token(bytes)));
+
+        assert!(!bool::from(a.ct_eq(&b)));
+    }
+
+    #[test]
+    fn test_ct_eq_integers() {
+        let a = SecretBox::new(Box::new(42u64));
+        let b = SecretBox::new(Box::new(42u64));
+        let c = SecretBox::new(Box::new(43u64));
+
+        assert!(bool::from(a.ct_eq(&b)));
+        assert!(!bool::from(a.ct_eq(&c)));
+    }
+
+    #[test]
+    fn test_eq_is_constant_time_eq() {
+        let a = SecretBox::new(Box::new(Token([7; 16])));
+        let b = SecretBox::new_protected(Box::new(Token([7; 16])));
+        let c = SecretBox::new(Box::new(Token([8; 16])));
+
+        assert!(a == b);
+        assert!(a != c);
+        assert_eq!(a == c, bool::from(a.ct_eq(&c)));
+    }
+
+    #[test]
+    fn test_ct_gt_integers() {
+        let small = SecretBox::new(Box::new(1u64));
+        let large = SecretBox::new(Box::new(u64::MAX));
+        let other_small = SecretBox::new(Box::new(1u64));
+
+        assert!(bool::from(large.ct_gt(&small)));
+        assert!(!bool::from(small.ct_gt(&large)));
+        assert!(!bool::from(small.ct_gt(&other_small)));
+
+        assert!(bool::from(small.ct_lt(&large)));
+        assert!(!bool::from(large.ct_lt(&small)));
+        assert!(!bool::from(small.ct_lt(&other_small)));
+    }
+
+    #[test]
+    fn test_conditional_select() {
+        let a = SecretBox::new(Box::new(0x1111_2222u64));
+        let b = SecretBox::new(Box::new(0x3333_4444u64));
+
+        let first = SecretBox::conditional_select(&a, &b, Choice::from(0));
+        let second = SecretBox::conditional_select(&a, &b, Choice::from(1));
+
+        assert_eq!(*first.expose_secret(), 0x1111_2222);
+        assert_eq!(*second.expose_secret(), 0x3333_4444);
+        assert_eq!(*a.expose_secret(), 0x1111_2222);
+        assert_eq!(*b.expose_secret(), 0x3333_4444);
+    }
+
+    #[test]
+    fn test_conditional_select_keeps_protection() {
+        let a = SecretBox::new_protected(Box::new(5u8));
+        let b = SecretBox::new_protected(Box::new(6u8));
+
+        let selected = SecretBox::conditional_select(&a, &b, Choice::from(1));
+
+        assert!(selected.protection.as_ref().unwrap().is_protected());
+        assert_eq!(*selected.expose_secret(), 6);
+    }
+
+    #[test]
+    fn test_ct_get_every_index() {
+        let bytes: Vec<u8> = (0..=255).rev().collect();
+        let vec = SecretBox::new(Box::new(bytes.clone()));
+        let array = SecretBox::new_protected(Box::new(<[u8; 256]>::try_from(&bytes[..]).unwrap()));
+
+        for (i, &byte) in bytes.iter().enumerate() {
+            assert_eq!(vec.ct_get(i), byte);
+            assert_eq!(array.ct_get(i), byte);
+        }
+    }
+
+    #[test]
+    fn test_ct_get_out_of_range() {
+        let secret = SecretBox::new(Box::new(vec![1u8, 2, 3]));
+
+        assert_eq!(secret.ct_get(3), 0);
+        assert_eq!(secret.ct_get(usize::MAX), 0);
+        assert_eq!(SecretBox::new(Box::new(Vec::<u8>::new())).ct_get(0), 0);
+    }
+
+    #[test]
+    fn test_verify_mac_matching() {
+        let mac = SecretBox::new_protected(Box::new([0x5au8; 32]));
+
+        assert!(mac.verify_mac(&[0x5a; 32]));
+    }
+
+    #[test]
+    fn test_verify_mac_not_matching() {
+        let mac = SecretBox::new(Box::new([0x5au8; 32]));
+        let mut last_byte_differs = [0x5a; 32];
+        last_byte_differs[31] ^= 1;
+
+        assert!(!mac.verify_mac(&last_byte_differs));
+        assert!(!mac.verify_mac(&[0; 32]));
+    }
+
+    fn secret(bytes: &[u8]) -> SecretBox<Vec<u8>> {
+        SecretBox::new(Box::new(bytes.to_vec()))
+    }
+
+    #[test]
+    fn test_ct_cmp_equal_lengths() {
+        let middle = secret(&[0x10, 0x80, 0x00]);
+
+        assert_eq!(middle.ct_cmp(&secret(&[0x10, 0x7f, 0xff])), Ordering::Greater);
+        assert_eq!(middle.ct_cmp(&secret(&[0x10, 0x80, 0x00])), Ordering::Equal);
+        assert_eq!(middle.ct_cmp(&secret(&[0x10, 0x80, 0x01])), Ordering::Less);
+        assert_eq!(middle.ct_cmp(&secret(&[0xff, 0x00, 0x00])), Ordering::Less);
+    }
+
+    #[test]
+    fn test_ct_cmp_matches_slice_ordering() {
+        let values = [[0u8, 0], [0, 1], [0, 0xff], [1, 0], [0x80, 0x7f], [0xff, 0xff]];
+
+        for a in &values {
+            for b in &values {
+                assert_eq!(secret(a).ct_cmp(&secret(b)), a.cmp(b), "{a:?} <=> {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_ct_cmp_unequal_lengths() {
+        assert_eq!(secret(&[1, 2]).ct_cmp(&secret(&[1, 2, 0])), Ordering::Less);
+        assert_eq!(secret(&[1, 3]).ct_cmp(&secret(&[1, 2, 0])), Ordering::Greater);
+        assert_eq!(secret(&[]).ct_cmp(&secret(&[])), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_ct_eq_slice_equal() {
+        let vec = SecretBox::new(Box::new(b"api-key".to_vec()));
+        let array = SecretBox::new(Box::new(*b"api-key"));
+
+        assert!(bool::from(vec.ct_eq_slice(b"api-key")));
+        assert!(bool::from(array.ct_eq_slice(b"api-key")));
+    }
+
+    #[test]
+    fn test_ct_eq_slice_unequal_same_length() {
+        let vec = SecretBox::new(Box::new(b"api-key".to_vec()));
+        let array = SecretBox::new(Box::new(*b"api-key"));
+
+        assert!(!bool::from(vec.ct_eq_slice(b"api-kez")));
+        assert!(!bool::from(array.ct_eq_slice(b"bpi-key")));
+    }
+
+    #[test]
+    fn test_ct_eq_slice_different_length() {
+        let secret = SecretBox::new(Box::new(b"api-key".to_vec()));
+
+        assert!(!bool::from(secret.ct_eq_slice(b"api")));
+        assert!(!bool::from(secret.ct_eq_slice(b"api-keyapi-key")));
+        assert!(!bool::from(secret.ct_eq_slice(b"")));
+        assert!(!bool::from(SecretBox::new(Box::new(Vec::new())).ct_eq_slice(b"a")));
+        assert!(bool::from(SecretBox::new(Box::new(Vec::new())).ct_eq_slice(b"")));
+    }
+
+    #[test]
+    fn test_ct_eq_secret_unequal_lengths() {
+        let key = SecretBox::new(Box::new(b"api-key".to_vec()));
+        let prefix = SecretBox::new(Box::new(b"api".to_vec()));
+        let extended = SecretBox::new(Box::new(b"api-key-2".to_vec()));
+        let wrapped = SecretBox::new(Box::new(b"api-keyapi-key".to_vec()));
+
+        assert!(bool::from(key.ct_eq_secret(&key.clone())));
+        assert!(!bool::from(key.ct_eq_secret(&prefix)));
+        assert!(!bool::from(prefix.ct_eq_secret(&key)));
+        assert!(!bool::from(key.ct_eq_secret(&extended)));
+        // The shorter secret is read wrapped around, which must not make it match.
+        assert!(!bool::from(key.ct_eq_secret(&wrapped)));
+    }
+
+    #[test]
+    fn test_ct_eq_wrong_length_looks_like_wrong_bytes() {
+        let secret = SecretBox::new(Box::new(b"api-key".to_vec()));
+
+        let wrong_length = secret.ct_eq_slice(b"api-key!");
+        let wrong_bytes = secret.ct_eq_slice(b"api-kez");
+
+        assert_eq!(wrong_length.unwrap_u8(), wrong_bytes.unwrap_u8());
+    }
+
+    #[test]
+    fn test_ct_eq_bytes_work_only_depends_on_other_length() {
+        use core::cell::Cell;
+
+        std::thread_local!(static COMPARED: Cell<usize> = const { Cell::new(0) });
+
+        #[derive(Clone, Copy, Default)]
+        struct Counted(u8);
+
+        impl ConstantTimeEq for Counted {
+            fn ct_eq(&self, other: &Self) -> Choice {
+                COMPARED.with(|compared| compared.set(compared.get() + 1));
+                self.0.ct_eq(&other.0)
+            }
+        }
+
+        let compared = |secret_len: usize, other_len: usize| {
+            COMPARED.with(|compared| compared.set(0));
+            ct_eq_bytes(&vec![Counted(1); secret_len], &vec![Counted(1); other_len]);
+            COMPARED.with(Cell::get)
+        };
+
+        for secret_len in [1, 7, 16, 64] {
+            assert_eq!(compared(secret_len, 16), 16);
+        }
+        assert_eq!(compared(0, 16), 16);
+    }
+}
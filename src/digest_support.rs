@@ -0,0 +1,68 @@
+//! Fingerprinting secrets, backed by the [`digest`] crate.
+
+use crate::SecretBox;
+use digest::{Digest, Output};
+use zeroize::Zeroize;
+
+impl<S: Zeroize + AsRef<[u8]>> SecretBox<S> {
+    /// Hash the secret with `D`, e.g. to identify a key in logs without revealing it.
+    ///
+    /// The secret is only exposed to feed the hasher, and the digest returned is not
+    /// secret itself. The hasher may keep the last partial block of the secret in its
+    /// internal buffer until it is dropped at the end of this call, use a hasher that
+    /// zeroizes itself, e.g. `sha2` with its `zeroize` feature, if that matters.
+    ///
+    /// A fingerprint of a low-entropy secret such as a password can be brute-forced,
+    /// only fingerprint keys and other random secrets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sha2::Sha256;
+    /// use shush::SecretBox;
+    ///
+    /// let key = SecretBox::new(Box::new([7u8; 32]));
+    /// let fingerprint = key.fingerprint::<Sha256>();
+    ///
+    /// assert_eq!(fingerprint.len(), 32);
+    /// assert_eq!(fingerprint, SecretBox::new(Box::new([7u8; 32])).fingerprint::<Sha256>());
+    /// ```
+    pub fn fingerprint<D: Digest>(&self) -> Output<D> {
+        self.with_secret(|secret| D::digest(secret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{boxed::Box, vec::Vec};
+    use sha2::{Sha256, Sha512};
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn hex(bytes: &[u8]) -> Vec<u8> {
+        bytes
+            .iter()
+            .flat_map(|byte| [byte >> 4, byte & 0xf])
+            .map(|nibble| b"0123456789abcdef"[nibble as usize])
+            .collect()
+    }
+
+    #[test]
+    fn test_fingerprint_known_digest() {
+        let array = SecretBox::new_protected(Box::new(*b"abc"));
+        let vec = SecretBox::new(Box::new(b"abc".to_vec()));
+
+        assert_eq!(hex(&array.fingerprint::<Sha256>()), ABC_SHA256.as_bytes());
+        assert_eq!(vec.fingerprint::<Sha256>(), array.fingerprint::<Sha256>());
+    }
+
+    #[test]
+    fn test_fingerprint_depends_on_secret_and_digest() {
+        let key = SecretBox::new(Box::new(*b"abc"));
+        let other = SecretBox::new(Box::new(*b"abd"));
+
+        assert_ne!(key.fingerprint::<Sha256>(), other.fingerprint::<Sha256>());
+        assert_eq!(key.fingerprint::<Sha512>().len(), 64);
+    }
+}
@@ -0,0 +1,111 @@
+//! Shannon entropy estimates of secrets, to reject weak user-provided secrets.
+
+use crate::{SecretBox, SecretString};
+use alloc::vec::Vec;
+use zeroize::Zeroize;
+
+impl SecretBox<Vec<u8>> {
+    /// Estimate the entropy of the secret in bits, e.g. to reject a weak key before
+    /// accepting it, without the bytes ever leaving the exposure.
+    ///
+    /// This is the Shannon entropy of the byte frequencies times the length, only the
+    /// estimate is returned. It ignores the order of the bytes, so structured secrets
+    /// such as `abcdefgh` score the same as random ones with as many distinct bytes. Use
+    /// it as a lower bar, not as a measure of strength.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// let weak = SecretBox::new(Box::new(vec![0u8; 32]));
+    /// let strong = SecretBox::new(Box::new((0..=255).collect::<Vec<u8>>()));
+    ///
+    /// assert_eq!(weak.estimate_entropy_bits(), 0.0);
+    /// assert_eq!(strong.estimate_entropy_bits(), 2048.0);
+    /// ```
+    pub fn estimate_entropy_bits(&self) -> f64 {
+        self.with_secret(|secret| entropy_bits(secret))
+    }
+}
+
+impl SecretString {
+    /// Estimate the entropy of the bytes of the string in bits, see
+    /// [`SecretBox::<Vec<u8>>::estimate_entropy_bits`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretString;
+    ///
+    /// let password = SecretString::from("aaaaaaaa");
+    ///
+    /// assert_eq!(password.estimate_entropy_bits(), 0.0);
+    /// ```
+    pub fn estimate_entropy_bits(&self) -> f64 {
+        self.with_secret(|secret| entropy_bits(secret.as_bytes()))
+    }
+}
+
+/// Shannon entropy of `bytes` in bits, the entropy per byte times their number.
+fn entropy_bits(bytes: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[usize::from(byte)] += 1;
+    }
+
+    let len = bytes.len() as f64;
+    let bits_per_byte: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+
+    // The histogram tells a lot about the secret.
+    counts.zeroize();
+
+    bits_per_byte * len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use rand_chacha::{
+        rand_core::{RngCore, SeedableRng},
+        ChaCha20Rng,
+    };
+
+    #[test]
+    fn test_entropy_of_zeroes_is_zero() {
+        let secret = SecretBox::new(Box::new(vec![0u8; 64]));
+        let empty = SecretBox::new(Box::new(Vec::new()));
+
+        assert_eq!(secret.estimate_entropy_bits(), 0.0);
+        assert_eq!(empty.estimate_entropy_bits(), 0.0);
+    }
+
+    #[test]
+    fn test_entropy_of_random_bytes_is_high() {
+        let mut bytes = vec![0u8; 4096];
+        ChaCha20Rng::seed_from_u64(0).fill_bytes(&mut bytes);
+        let secret = SecretBox::new(Box::new(bytes));
+
+        // Close to the 8 bits per byte of uniformly random bytes.
+        let bits = secret.estimate_entropy_bits();
+        assert!(bits > 7.9 * 4096.0 && bits <= 8.0 * 4096.0, "{bits}");
+    }
+
+    #[test]
+    fn test_entropy_of_string() {
+        let weak = SecretString::from("passwordpassword");
+        let strong = SecretString::from("k7#Qz!9vLm2@xR4p");
+
+        // `password` has 7 distinct letters, each of the 16 characters is distinct.
+        assert!(weak.estimate_entropy_bits() < 16.0 * 3.0);
+        assert_eq!(strong.estimate_entropy_bits(), 16.0 * 4.0);
+    }
+}
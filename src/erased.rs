@@ -0,0 +1,119 @@
+//! Secrets whose type is erased, for stores holding secrets of different types.
+
+use crate::SecretBox;
+use alloc::boxed::Box;
+use core::{
+    any::{self, Any},
+    fmt::{self, Debug},
+};
+use zeroize::Zeroize;
+
+/// Secret of any type, recovered as its concrete [`SecretBox`] with
+/// [`Self::downcast`], e.g. in a registry of keys and passwords together.
+///
+/// The secret stays in its locked allocation throughout, erasing and downcasting only
+/// move the box that points to it, so it is never unlocked or copied in between. It is
+/// zeroized when dropped, whether or not it was downcast.
+///
+/// # Examples
+///
+/// ```
+/// use shush::{AnySecret, ExposeSecret, SecretBox, SecretString};
+///
+/// let store = vec![
+///     AnySecret::from(SecretBox::new(Box::new([7u8; 32]))),
+///     AnySecret::from(SecretString::from("hunter2")),
+/// ];
+///
+/// for secret in store {
+///     match secret.downcast::<[u8; 32]>() {
+///         Ok(key) => assert_eq!(*key.expose_secret(), [7; 32]),
+///         Err(secret) => assert_eq!(
+///             &*secret.downcast::<String>().unwrap().expose_str(),
+///             "hunter2"
+///         ),
+///     }
+/// }
+/// ```
+pub struct AnySecret {
+    secret: Box<dyn Any + Send + Sync>,
+    type_name: &'static str,
+    debug_label: &'static str,
+}
+
+impl AnySecret {
+    /// Whether the secret is a `SecretBox<T>`.
+    pub fn is<T: Zeroize + 'static>(&self) -> bool {
+        self.secret.is::<SecretBox<T>>()
+    }
+
+    /// Recover the secret as a `SecretBox<T>`, keeping its locked allocation, or give it
+    /// back if it is of another type.
+    pub fn downcast<T: Zeroize + 'static>(self) -> Result<SecretBox<T>, Self> {
+        match self.secret.downcast::<SecretBox<T>>() {
+            Ok(secret) => Ok(*secret),
+            Err(secret) => Err(Self { secret, ..self }),
+        }
+    }
+}
+
+impl<S: Zeroize + Send + Sync + 'static> From<SecretBox<S>> for AnySecret {
+    /// Erase the type of an existing secret, keeping its locked allocation.
+    fn from(secret: SecretBox<S>) -> Self {
+        Self {
+            type_name: any::type_name::<S>(),
+            debug_label: secret.debug_label,
+            secret: Box::new(secret),
+        }
+    }
+}
+
+impl Debug for AnySecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AnySecret<{}>({})", self.type_name, self.debug_label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lock, ExposeSecret, SecretString};
+    use alloc::string::String;
+
+    #[test]
+    fn test_any_secret_downcasts_each_type() {
+        let key = SecretBox::new_protected(Box::new([7u8; 32]));
+        let key_ptr = key.with_secret(|key| key.as_ptr());
+        let store = vec![AnySecret::from(key), AnySecret::from(SecretString::from("hunter2"))];
+        let calls = lock::CALLS.with(|calls| calls.get());
+
+        let mut store = store.into_iter();
+        let key = store.next().unwrap().downcast::<[u8; 32]>().unwrap();
+        let password = store.next().unwrap().downcast::<String>().unwrap();
+
+        // Neither secret was unlocked or locked again.
+        assert_eq!(lock::CALLS.with(|calls| calls.get()), calls);
+        assert_eq!(key.with_secret(|key| key.as_ptr()), key_ptr);
+        assert_eq!(*key.expose_secret(), [7; 32]);
+        assert_eq!(&*password.expose_str(), "hunter2");
+    }
+
+    #[test]
+    fn test_any_secret_wrong_type_gives_it_back() {
+        let secret = AnySecret::from(SecretBox::new(Box::new(42u64)));
+        assert!(secret.is::<u64>() && !secret.is::<u32>());
+
+        let secret = secret.downcast::<u32>().unwrap_err();
+
+        assert_eq!(*secret.downcast::<u64>().unwrap().expose_secret(), 42);
+    }
+
+    #[test]
+    fn test_any_secret_debug_is_redacted() {
+        let secret = AnySecret::from(SecretBox::new(Box::new(42u64)));
+        let labeled = AnySecret::from(SecretBox::new(Box::new(7u8)).with_debug_label("pin"));
+
+        assert_eq!(format!("{secret:?}"), "AnySecret<u64>([REDACTED])");
+        assert_eq!(format!("{labeled:?}"), "AnySecret<u8>(pin)");
+    }
+}
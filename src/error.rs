@@ -0,0 +1,101 @@
+//! Errors returned when the memory backing a secret cannot be protected.
+
+use core::{error::Error, fmt};
+#[cfg(feature = "std")]
+use std::io;
+
+/// Returned when the memory of a secret could not be locked.
+///
+/// The error only records the platform error and the size of the region that
+/// failed to lock, never the secret itself.
+///
+/// On Windows a process can only lock as many pages as fit in its minimum working
+/// set. Locking grows the working set once before giving up, so an error there usually
+/// means the working set could not be grown, see `SetProcessWorkingSetSize`.
+#[derive(Debug)]
+pub struct LockError {
+    len: usize,
+    #[cfg(feature = "std")]
+    source: io::Error,
+}
+
+impl LockError {
+    /// Build a `LockError` from the last OS error for a region of `len` bytes.
+    pub(crate) fn last_os_error(len: usize) -> Self {
+        Self {
+            len,
+            #[cfg(feature = "std")]
+            source: io::Error::last_os_error(),
+        }
+    }
+
+    /// Number of bytes that failed to lock.
+    pub fn size(&self) -> usize {
+        self.len
+    }
+
+    /// Platform error reported by the lock call.
+    #[cfg(feature = "std")]
+    pub fn os_error(&self) -> &io::Error {
+        &self.source
+    }
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unable to lock {} bytes of memory", self.len)?;
+
+        #[cfg(feature = "std")]
+        write!(f, ": {}", self.source)?;
+
+        #[cfg(windows)]
+        write!(
+            f,
+            " (the process working set may be too small, see SetProcessWorkingSetSize)"
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Error for LockError {
+    #[cfg(feature = "std")]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Returned when a secret is larger than the maximum it was created with, see
+/// [`SecretBox::new_bounded`](crate::SecretBox::new_bounded).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeError {
+    pub(crate) size: usize,
+    pub(crate) max: usize,
+}
+
+impl SizeError {
+    /// Size of the secret in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Maximum size the secret was allowed, in bytes.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "secret of {} bytes exceeds the maximum of {} bytes", self.size, self.max)
+    }
+}
+
+impl Error for SizeError {}
+
+#[cfg(feature = "std")]
+impl From<LockError> for io::Error {
+    fn from(err: LockError) -> Self {
+        io::Error::new(err.source.kind(), err)
+    }
+}
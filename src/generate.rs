@@ -0,0 +1,59 @@
+//! Generating random secrets in place from a [`RngCore`].
+
+use crate::{lock, ExposeSecret, SecretBox, SecretVec};
+use rand_core::RngCore;
+
+impl<const N: usize> SecretBox<[u8; N]> {
+    /// Generate a random secret array, e.g. a symmetric key.
+    ///
+    /// The array is allocated and locked first, then filled in place, so the random
+    /// bytes never exist outside of locked memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    pub fn generate(rng: &mut impl RngCore) -> Self {
+        let mut secret = Self::new_array_zeroed();
+        rng.fill_bytes(&mut *secret.expose_secret_mut());
+        secret
+    }
+}
+
+impl SecretVec {
+    /// Generate `len` random bytes, filled in place like [`SecretBox::generate`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    pub fn generate(rng: &mut impl RngCore, len: usize) -> Self {
+        let mut secret = SecretBox::try_zeroed(len).unwrap_or_else(lock::failed);
+        rng.fill_bytes(&mut secret.expose_secret_mut());
+        Self(secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_generate_array_is_deterministic() {
+        let first = SecretBox::<[u8; 32]>::generate(&mut ChaCha20Rng::seed_from_u64(42));
+        let second = SecretBox::<[u8; 32]>::generate(&mut ChaCha20Rng::seed_from_u64(42));
+
+        assert_eq!(*first.expose_secret(), *second.expose_secret());
+        assert!(first.expose_secret().iter().any(|&byte| byte != 0));
+    }
+
+    #[test]
+    fn test_generate_vec() {
+        let first = SecretVec::generate(&mut ChaCha20Rng::seed_from_u64(42), 64);
+        let second = SecretVec::generate(&mut ChaCha20Rng::seed_from_u64(42), 64);
+
+        assert_eq!(first.len(), 64);
+        assert_eq!(&*first.expose_as_slice(), &*second.expose_as_slice());
+        assert!(first.expose_as_slice().iter().any(|&byte| byte != 0));
+    }
+}
@@ -0,0 +1,104 @@
+//! Hashing secrets through a keyed digest, so they can be used as `HashMap` keys.
+
+use crate::SecretBox;
+use core::hash::{BuildHasher, Hash, Hasher};
+use std::{collections::hash_map::RandomState, sync::OnceLock};
+use zeroize::Zeroize;
+
+/// SipHash key of the process, picked at random the first time a secret is hashed.
+fn process_key() -> &'static RandomState {
+    static KEY: OnceLock<RandomState> = OnceLock::new();
+
+    KEY.get_or_init(RandomState::new)
+}
+
+/// Hash the secret as its SipHash digest under a random key of the process.
+///
+/// The hasher passed in, e.g. the one of a `HashMap`, only ever sees that 64-bit digest,
+/// never the secret, so an unkeyed or weak hasher can't leak it. SipHash takes the same
+/// time for any secret of a given length. Equal secrets hash the same within a process,
+/// but their hashes differ between processes and are not fit to persist. The SipHash
+/// state is not zeroized, it holds no more than a few trailing bytes of the secret.
+///
+/// Without the `hash` feature secrets deliberately don't implement `Hash`. Keying a
+/// `HashMap` also takes `Eq`, which the `subtle` feature implements for secrets whose
+/// type implements `subtle::ConstantTimeEq`. Clippy's `mutable_key_type` lint flags such
+/// maps because of the atomic guard count of secrets, which doesn't affect the hash.
+///
+/// # Examples
+///
+/// ```
+/// use shush::SecretBox;
+/// use std::hash::{BuildHasher, RandomState};
+///
+/// let hasher = RandomState::new();
+/// let nonce = SecretBox::new(Box::new(*b"nonce-1"));
+///
+/// assert_eq!(hasher.hash_one(&nonce), hasher.hash_one(&nonce.clone()));
+/// ```
+impl<S: Zeroize + Hash> Hash for SecretBox<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.with_secret(|secret| process_key().hash_one(secret)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{boxed::Box, vec::Vec};
+
+    /// Hasher recording every byte it is given.
+    #[derive(Default)]
+    struct Recorder(Vec<u8>);
+
+    impl Hasher for Recorder {
+        fn finish(&self) -> u64 {
+            0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.extend_from_slice(bytes);
+        }
+    }
+
+    fn recorded<T: Hash>(value: &T) -> Vec<u8> {
+        let mut recorder = Recorder::default();
+        value.hash(&mut recorder);
+
+        recorder.0
+    }
+
+    #[test]
+    fn test_hash_only_writes_keyed_digest() {
+        let secret = SecretBox::new(Box::new([0xa5u8; 32]));
+
+        let written = recorded(&secret);
+
+        assert_eq!(written.len(), 8);
+        assert_ne!(written, recorded(&[0xa5u8; 32])[..8]);
+    }
+
+    #[test]
+    fn test_hash_equal_secrets_hash_equal() {
+        let a = SecretBox::new(Box::new(*b"api-key"));
+        let b = SecretBox::new_protected(Box::new(*b"api-key"));
+        let other = SecretBox::new(Box::new(*b"api-kez"));
+
+        assert_eq!(recorded(&a), recorded(&b));
+        assert_ne!(recorded(&a), recorded(&other));
+    }
+
+    #[cfg(feature = "subtle")]
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn test_hash_map_keyed_by_secret() {
+        use std::collections::HashMap;
+
+        let mut sessions = HashMap::new();
+        sessions.insert(SecretBox::new(Box::new(1u64)), "alice");
+        sessions.insert(SecretBox::new(Box::new(2u64)), "bob");
+
+        assert_eq!(sessions[&SecretBox::new(Box::new(2u64))], "bob");
+        assert_eq!(sessions.get(&SecretBox::new(Box::new(3u64))), None);
+    }
+}
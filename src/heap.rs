@@ -0,0 +1,744 @@
+//! Locking the heap buffer owned by secrets such as `Vec<u8>`.
+//!
+//! A [`SecretBox`] locks the memory of the value it holds, which for a `Vec<u8>` is only
+//! its pointer, length and capacity. The constructors here also lock the buffer the
+//! vector points to. Growing the vector through [`ExposeSecret::expose_secret_mut`]
+//! reallocates it outside of the locked buffer, so size it upfront or make room with
+//! [`SecretBox::reserve_locked`] first.
+
+use crate::{advise_locked, lock, page, release_locked, ExposeSecret, LockError, SecretBox};
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+use core::mem;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Heap buffer owned by a secret and locked along with it.
+pub(crate) struct LockedHeap<S> {
+    pub(crate) ptr: *mut u8,
+    pub(crate) len: usize,
+    /// Bytes from `ptr` that are still locked, less than `len` once
+    /// [`SecretBox::truncate_locked`] unlocked the pages past them.
+    pub(crate) locked_len: usize,
+    /// Finds the buffer in the secret, so clones can lock theirs.
+    pub(crate) buffer: fn(&mut S) -> (*mut u8, usize),
+}
+
+impl<S: Zeroize> SecretBox<S> {
+    /// Lock the heap buffer `buffer` finds in the secret until the secret is dropped.
+    ///
+    /// A buffer locked before is unlocked first, unless it moved since, then it was
+    /// freed by its owner already and is only forgotten.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must return the start and length of an allocation owned by the secret.
+    pub(crate) unsafe fn lock_heap(
+        &mut self,
+        buffer: fn(&mut S) -> (*mut u8, usize),
+    ) -> Result<(), LockError> {
+        let (ptr, len) = self.with_secret_mut(buffer);
+
+        if let Some(heap) = self.locked_heap.take() {
+            if (heap.ptr, heap.len) == (ptr, len) {
+                release_locked(heap.ptr, heap.len);
+            }
+        }
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        lock::lock(ptr, len)?;
+        advise_locked(ptr, len);
+        self.locked_heap = Some(LockedHeap {
+            ptr,
+            len,
+            locked_len: len,
+            buffer,
+        });
+
+        Ok(())
+    }
+}
+
+/// Assert that the heap buffer of `secret` is the one locked, and return it.
+#[cfg(test)]
+pub(crate) fn assert_heap_locked<S: Zeroize>(secret: &mut SecretBox<S>) -> (*mut u8, usize) {
+    let heap = secret.locked_heap.as_ref().expect("heap buffer not locked");
+    let (locked, buffer) = ((heap.ptr, heap.len), heap.buffer);
+
+    let buffer = secret.with_secret_mut(buffer);
+    assert_eq!(buffer, locked);
+
+    buffer
+}
+
+/// Buffer of a `Vec<u8>`, including its spare capacity.
+pub(crate) fn vec_buffer(vec: &mut Vec<u8>) -> (*mut u8, usize) {
+    (vec.as_mut_ptr(), vec.capacity())
+}
+
+/// Buffer of a `Box<[u8]>`.
+pub(crate) fn boxed_slice_buffer(slice: &mut Box<[u8]>) -> (*mut u8, usize) {
+    (slice.as_mut_ptr(), slice.len())
+}
+
+/// Buffer of a `String`, including its spare capacity.
+pub(crate) fn string_buffer(string: &mut String) -> (*mut u8, usize) {
+    (string.as_mut_ptr(), string.capacity())
+}
+
+impl SecretBox<Vec<u8>> {
+    /// Create a secret of `len` zeroed bytes, with its buffer locked.
+    pub(crate) fn try_zeroed(len: usize) -> Result<Self, LockError> {
+        Self::try_from_locked_vec(vec![0; len])
+    }
+
+    /// Create an empty secret with a locked buffer of at least `capacity` bytes.
+    pub(crate) fn try_with_capacity(capacity: usize) -> Result<Self, LockError> {
+        Self::try_from_locked_vec(Vec::with_capacity(capacity))
+    }
+
+    fn try_from_locked_vec(vec: Vec<u8>) -> Result<Self, LockError> {
+        let mut secret = Self::try_new(Box::new(vec))?;
+        unsafe { secret.lock_heap(vec_buffer)? };
+
+        Ok(secret)
+    }
+
+    /// Make room for at least `additional` more bytes in the locked buffer, so they can
+    /// be pushed through [`ExposeSecret::expose_secret_mut`] without reallocating.
+    ///
+    /// If the buffer has to grow, the bytes are copied into a new buffer locked
+    /// beforehand and the old buffer is zeroized before it is freed. Like
+    /// [`Vec::reserve`] it may reserve more to avoid frequent reallocations. A buffer
+    /// that was not locked yet is moved to a locked one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new buffer cannot be locked, see [`Self::try_reserve_locked`] for
+    /// a fallible version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let mut key = SecretBox::from_slice_zeroizing(&mut [1, 2]);
+    ///
+    /// key.reserve_locked(2);
+    /// key.expose_secret_mut().extend_from_slice(&[3, 4]);
+    ///
+    /// assert_eq!(*key.expose_secret(), [1, 2, 3, 4]);
+    /// ```
+    pub fn reserve_locked(&mut self, additional: usize) {
+        self.try_reserve_locked(additional).unwrap_or_else(lock::failed)
+    }
+
+    /// Same as [`Self::reserve_locked`], but returns an error if the new buffer cannot
+    /// be locked, leaving the secret unchanged.
+    pub fn try_reserve_locked(&mut self, additional: usize) -> Result<(), LockError> {
+        let (len, buffer) = self.with_secret_mut(|vec| (vec.len(), vec_buffer(vec)));
+        let capacity = buffer.1;
+        let required = len.checked_add(additional).expect("capacity overflow");
+
+        self.forget_moved_heap(buffer);
+
+        let locked = match &self.locked_heap {
+            Some(heap) => required <= heap.locked_len,
+            None => capacity == 0,
+        };
+
+        if required > capacity || !locked {
+            // Grow geometrically like `Vec` does, so appending stays amortized.
+            drop(self.relocate(required.max(capacity.saturating_mul(2)))?);
+        }
+
+        Ok(())
+    }
+
+    /// Shorten the secret to `len` bytes, zeroizing the rest of the buffer and unlocking
+    /// its pages that are no longer needed, to give the locked memory back to the
+    /// process.
+    ///
+    /// Only whole pages past the new length are unlocked, a page still holding part of
+    /// the secret, or memory past the buffer, stays locked. The buffer keeps its
+    /// capacity, [`Self::reserve_locked`] moves the secret to a locked buffer again if
+    /// it has to grow into unlocked pages. Nothing happens if `len` is not shorter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let mut secret = SecretBox::from_slice_zeroizing(&mut vec![1; 64 << 10]);
+    ///
+    /// secret.truncate_locked(32);
+    ///
+    /// assert_eq!(*secret.expose_secret(), [1; 32]);
+    /// ```
+    pub fn truncate_locked(&mut self, len: usize) {
+        if len >= self.with_secret(Vec::len) {
+            return;
+        }
+
+        // Zeroize before unlocking, like on drop.
+        let buffer = self.with_secret_mut(|vec| {
+            vec.truncate(len);
+            vec.spare_capacity_mut().zeroize();
+            vec_buffer(vec)
+        });
+
+        self.forget_moved_heap(buffer);
+        let Some(heap) = self.locked_heap.as_mut() else {
+            return;
+        };
+        let page_size = page::page_size();
+        let start = (heap.ptr as usize + len).next_multiple_of(page_size);
+        let end = (heap.ptr as usize + heap.locked_len) & !(page_size - 1);
+
+        if start < end {
+            unsafe { release_locked(start as *mut u8, end - start) };
+            heap.locked_len = start - heap.ptr as usize;
+        }
+    }
+
+    /// Forget the locked buffer if `buffer`, the current one, is another, e.g. after
+    /// growing the vector through [`ExposeSecret::expose_secret_mut`]. The vector freed
+    /// the locked buffer already, so it is not unlocked.
+    fn forget_moved_heap(&mut self, buffer: (*mut u8, usize)) {
+        if self.locked_heap.as_ref().is_some_and(|heap| (heap.ptr, heap.len) != buffer) {
+            self.locked_heap = None;
+        }
+    }
+
+    /// Move the secret to a new locked buffer of at least `capacity` bytes, returning
+    /// the old buffer zeroized and unlocked.
+    fn relocate(&mut self, capacity: usize) -> Result<Vec<u8>, LockError> {
+        let mut grown = Vec::with_capacity(capacity);
+        let (ptr, len) = vec_buffer(&mut grown);
+
+        unsafe {
+            lock::lock(ptr, len)?;
+            advise_locked(ptr, len);
+        }
+
+        let mut old = self.with_secret_mut(|vec| {
+            grown.extend_from_slice(vec);
+            mem::replace(vec, grown)
+        });
+        old.zeroize();
+
+        if let Some(heap) = self.locked_heap.take() {
+            // A buffer that moved since it was locked was freed already.
+            if (heap.ptr, heap.len) == vec_buffer(&mut old) {
+                unsafe { release_locked(heap.ptr, heap.len) };
+            }
+        }
+        self.locked_heap = Some(LockedHeap {
+            ptr,
+            len,
+            locked_len: len,
+            buffer: vec_buffer,
+        });
+
+        Ok(old)
+    }
+
+    /// Copy `src` into a new secret and zeroize `src` in place, e.g. a buffer a key was
+    /// just read into.
+    ///
+    /// The buffer of the secret is locked before the bytes are copied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, `src` is zeroized anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let mut buf = [1, 2, 3];
+    /// let key = SecretBox::from_slice_zeroizing(&mut buf);
+    ///
+    /// assert_eq!(buf, [0; 3]);
+    /// assert_eq!(*key.expose_secret(), [1, 2, 3]);
+    /// ```
+    pub fn from_slice_zeroizing(src: &mut [u8]) -> Self {
+        let secret = Self::try_zeroed(src.len()).map(|mut secret| {
+            secret.expose_secret_mut().copy_from_slice(src);
+            secret
+        });
+        src.zeroize();
+
+        secret.unwrap_or_else(lock::failed)
+    }
+
+    /// Create a secret from the first `len` bytes `iter` yields, e.g. the output of a key
+    /// derivation producing bytes lazily.
+    ///
+    /// A buffer of `len` bytes is locked before the first byte is taken, and the bytes
+    /// are written straight into it, so they are never collected into an unlocked `Vec`
+    /// first. At most `len` bytes are taken, and the secret is shorter if `iter` ends
+    /// early. The rest of `iter` is neither consumed nor zeroized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer cannot be locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let key = SecretBox::from_byte_iter(4, [1, 2, 3, 4, 5].into_iter());
+    ///
+    /// assert_eq!(*key.expose_secret(), [1, 2, 3, 4]);
+    /// ```
+    pub fn from_byte_iter(len: usize, iter: impl Iterator<Item = u8>) -> Self {
+        let mut secret = Self::try_with_capacity(len).unwrap_or_else(lock::failed);
+
+        // At most `len` bytes are taken, so the locked buffer never grows.
+        secret.with_secret_mut(|vec| vec.extend(iter.take(len)));
+
+        secret
+    }
+
+    /// Call `f` with every byte of the secret in order, while it is exposed, see
+    /// [`SecretBox::<[u8; N]>::for_each_byte`](SecretBox::for_each_byte).
+    pub fn for_each_byte(&self, f: impl FnMut(u8)) {
+        self.with_secret(|secret| secret.iter().copied().for_each(f))
+    }
+
+    /// Read exactly `len` bytes from `r` into a new secret.
+    ///
+    /// The buffer is allocated and locked before anything is read, so the bytes go
+    /// straight into locked memory instead of an intermediate buffer. On error the bytes
+    /// read so far are zeroized.
+    #[cfg(feature = "std")]
+    pub fn try_from_reader(r: &mut impl Read, len: usize) -> io::Result<Self> {
+        let mut secret = Self::try_zeroed(len)?;
+
+        r.read_exact(&mut secret.expose_secret_mut())?;
+
+        Ok(secret)
+    }
+
+    /// Replace the secret with everything `r` yields until its end, e.g. a rotated key,
+    /// returning the number of bytes read.
+    ///
+    /// The bytes are read straight into the locked buffer, reusing it when they fit.
+    /// Old bytes past the new length are zeroized. If the new bytes don't fit, the
+    /// buffer grows like with [`Self::reserve_locked`], so it is relocated into a new
+    /// locked buffer and the old one is zeroized. On error the secret is left empty
+    /// and every byte read so far is zeroized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let mut key = SecretBox::from_slice_zeroizing(&mut [1; 32]);
+    ///
+    /// let read = key.overwrite_from_reader(&mut &[2u8; 16][..]).unwrap();
+    ///
+    /// assert_eq!(read, 16);
+    /// assert_eq!(*key.expose_secret(), [2; 16]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn overwrite_from_reader(&mut self, r: &mut impl Read) -> io::Result<usize> {
+        let result = self.try_overwrite_from_reader(r);
+
+        self.with_secret_mut(|vec| match result {
+            Ok(len) => {
+                vec[len..].zeroize();
+                vec.truncate(len);
+            }
+            Err(_) => vec.zeroize(),
+        });
+
+        result
+    }
+
+    /// Read `r` into the whole buffer, growing it when full, and return how many bytes
+    /// were read, leaving the buffer at its full capacity.
+    #[cfg(feature = "std")]
+    fn try_overwrite_from_reader(&mut self, r: &mut impl Read) -> io::Result<usize> {
+        // Make sure bytes are never read into a buffer that isn't locked.
+        self.try_reserve_locked(0)?;
+        self.with_secret_mut(|vec| vec.resize(vec.capacity(), 0));
+
+        let mut len = 0;
+
+        loop {
+            if len == self.with_secret(Vec::len) {
+                self.try_reserve_locked(1)?;
+                self.with_secret_mut(|vec| vec.resize(vec.capacity(), 0));
+            }
+
+            match self.with_secret_mut(|vec| r.read(&mut vec[len..])) {
+                Ok(0) => return Ok(len),
+                Ok(read) => len += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl SecretBox<Box<[u8]>> {
+    /// Take the bytes back out of the secret, without copying them.
+    ///
+    /// The buffer is unlocked and handed back wrapped in [`Zeroizing`], so it is still
+    /// zeroized once dropped, but may be swapped to disk from now on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// let key = SecretBox::from(vec![1u8, 2, 3].into_boxed_slice());
+    /// let bytes = key.into_inner_zeroizing();
+    ///
+    /// assert_eq!(**bytes, [1, 2, 3]);
+    /// ```
+    pub fn into_inner_zeroizing(mut self) -> Zeroizing<Box<[u8]>> {
+        let mut bytes = self.with_secret_mut(mem::take);
+
+        // A slice replaced through `expose_secret_mut` freed the locked one already.
+        if let Some(heap) = self.locked_heap.take() {
+            if (heap.ptr, heap.len) == boxed_slice_buffer(&mut bytes) {
+                unsafe { release_locked(heap.ptr, heap.len) };
+            }
+        }
+
+        // Dropping the secret only zeroizes the empty slice left behind.
+        Zeroizing::new(bytes)
+    }
+}
+
+impl From<Box<[u8]>> for SecretBox<Box<[u8]>> {
+    /// Take over the bytes of `slice`, locking its buffer in place without copying it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    fn from(slice: Box<[u8]>) -> Self {
+        let mut secret = Self::new(Box::new(slice));
+        unsafe { secret.lock_heap(boxed_slice_buffer) }.unwrap_or_else(lock::failed);
+
+        secret
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_try_from_reader() {
+        let mut reader = Cursor::new(vec![1, 2, 3, 4, 5]);
+
+        let secret = SecretBox::try_from_reader(&mut reader, 4).unwrap();
+
+        assert_eq!(*secret.expose_secret(), vec![1, 2, 3, 4]);
+        assert!(secret.locked_heap.is_some());
+    }
+
+    #[test]
+    fn test_from_byte_iter_fills_locked_buffer() {
+        let mut secret = SecretBox::from_byte_iter(64, 0u8..);
+        let short = SecretBox::from_byte_iter(64, 0u8..16);
+
+        let heap = secret.locked_heap.as_ref().unwrap();
+        let locked = (heap.ptr, heap.len);
+        assert_eq!(secret.with_secret_mut(vec_buffer), locked);
+        assert_eq!(*secret.expose_secret(), (0..64).collect::<Vec<u8>>());
+        assert_eq!(*short.expose_secret(), (0..16).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_truncate_locked_unlocks_whole_pages_past_len() {
+        let page_size = page::page_size();
+        let mut secret = SecretBox::try_zeroed(8 * page_size).unwrap();
+        secret.expose_secret_mut().fill(1);
+        let (ptr, len) = secret.with_secret_mut(vec_buffer);
+
+        secret.truncate_locked(10);
+
+        let heap = secret.locked_heap.as_ref().unwrap();
+        let kept = (ptr as usize + 10).next_multiple_of(page_size) - ptr as usize;
+        assert_eq!((heap.ptr, heap.len, heap.locked_len), (ptr, len, kept));
+        assert_eq!(*secret.expose_secret(), [1; 10]);
+        let buffer = unsafe { core::slice::from_raw_parts(ptr, len) };
+        assert!(buffer[10..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_truncate_locked_keeps_shared_pages() {
+        let mut secret = SecretBox::from_slice_zeroizing(&mut [1; 100]);
+
+        secret.truncate_locked(10);
+        secret.truncate_locked(20);
+
+        let heap = secret.locked_heap.as_ref().unwrap();
+        assert_eq!(heap.locked_len, heap.len);
+        assert_eq!(*secret.expose_secret(), [1; 10]);
+    }
+
+    #[test]
+    fn test_reserve_locked_after_truncate_relocates_into_locked_buffer() {
+        let page_size = page::page_size();
+        let mut secret = SecretBox::try_zeroed(4 * page_size).unwrap();
+        let before = secret.with_secret_mut(vec_buffer);
+        secret.truncate_locked(1);
+
+        secret.reserve_locked(1);
+        assert_eq!(secret.with_secret_mut(vec_buffer), before);
+
+        secret.reserve_locked(3 * page_size);
+        let heap = secret.locked_heap.as_ref().unwrap();
+        assert_ne!(heap.ptr, before.0);
+        assert_eq!(heap.locked_len, heap.len);
+        assert_eq!(*secret.expose_secret(), [0]);
+    }
+
+    #[test]
+    fn test_overwrite_from_reader_shorter_reuses_buffer() {
+        let mut secret = SecretBox::from_slice_zeroizing(&mut [1; 8]);
+        let before = secret.with_secret_mut(vec_buffer);
+
+        let read = secret.overwrite_from_reader(&mut Cursor::new(vec![2; 3])).unwrap();
+
+        assert_eq!(read, 3);
+        assert_eq!(*secret.expose_secret(), [2; 3]);
+        assert_eq!(secret.with_secret_mut(vec_buffer), before);
+
+        let buffer = unsafe { core::slice::from_raw_parts(before.0, before.1) };
+        assert_eq!(buffer[..3], [2; 3]);
+        assert!(buffer[3..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_overwrite_from_reader_longer_relocks() {
+        let mut secret = SecretBox::from_slice_zeroizing(&mut [1; 8]);
+        let old_ptr = secret.with_secret_mut(vec_buffer).0;
+
+        let read = secret.overwrite_from_reader(&mut Cursor::new(vec![3; 100])).unwrap();
+
+        assert_eq!(read, 100);
+        assert_eq!(*secret.expose_secret(), [3; 100]);
+
+        let buffer = assert_heap_locked(&mut secret);
+        assert_ne!(buffer.0, old_ptr);
+    }
+
+    #[test]
+    fn test_overwrite_from_reader_error_clears_secret() {
+        struct Failing(usize);
+
+        impl Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0 {
+                    0 => Err(io::Error::other("connection reset")),
+                    _ => {
+                        self.0 -= 1;
+                        buf[0] = 9;
+                        Ok(1)
+                    }
+                }
+            }
+        }
+
+        let mut secret = SecretBox::from_slice_zeroizing(&mut [1; 8]);
+        let (ptr, capacity) = secret.with_secret_mut(vec_buffer);
+
+        let err = secret.overwrite_from_reader(&mut Failing(4)).unwrap_err();
+
+        assert_eq!(err.to_string(), "connection reset");
+        assert!(secret.expose_secret().is_empty());
+        let buffer = unsafe { core::slice::from_raw_parts(ptr, capacity) };
+        assert!(buffer.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_clone_locks_heap() {
+        let mut secret = SecretBox::try_from_reader(&mut Cursor::new(vec![7; 16]), 16).unwrap();
+
+        let clone = secret.clone();
+        let (secret_heap, clone_heap) = (
+            secret.locked_heap.as_ref().unwrap().ptr,
+            clone.locked_heap.as_ref().unwrap().ptr,
+        );
+        assert_ne!(secret_heap, clone_heap);
+
+        secret.zeroize();
+        drop(secret);
+
+        assert_eq!(*clone.expose_secret(), vec![7; 16]);
+    }
+
+    #[test]
+    fn test_try_from_reader_short_input() {
+        let mut reader = Cursor::new(vec![1, 2]);
+
+        let err = SecretBox::try_from_reader(&mut reader, 4).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_from_slice_zeroizing() {
+        let mut src = vec![9u8; 24];
+
+        let secret = SecretBox::from_slice_zeroizing(&mut src);
+
+        assert_eq!(src, [0; 24]);
+        assert_eq!(*secret.expose_secret(), [9; 24]);
+        assert!(secret.locked_heap.is_some());
+    }
+
+    #[test]
+    fn test_for_each_byte_xor() {
+        let secret = SecretBox::from_slice_zeroizing(&mut [0xff, 0x00, 0x0f]);
+        let mut out = [0xaa; 3];
+
+        let mut bytes = out.iter_mut();
+        secret.for_each_byte(|byte| *bytes.next().unwrap() ^= byte);
+
+        assert_eq!(out, [0x55, 0xaa, 0xa5]);
+    }
+
+    #[test]
+    fn test_reserve_locked_keeps_pushes_locked() {
+        let mut secret = SecretBox::from_slice_zeroizing(&mut [0xaa]);
+        let mut relocations = 0;
+
+        for byte in 0..1000u32 {
+            let before = secret.with_secret_mut(vec_buffer);
+            secret.reserve_locked(1);
+            secret.expose_secret_mut().push(byte as u8);
+
+            let buffer = assert_heap_locked(&mut secret);
+            relocations += usize::from(buffer != before);
+        }
+
+        assert_eq!(secret.with_secret(Vec::len), 1001);
+        assert!(relocations < 20, "grew {relocations} times");
+    }
+
+    #[test]
+    fn test_reserve_locked_locks_unlocked_buffer() {
+        let mut secret = SecretBox::new(Box::new(Vec::with_capacity(8)));
+        assert!(secret.locked_heap.is_none());
+
+        secret.reserve_locked(0);
+
+        assert_heap_locked(&mut secret);
+    }
+
+    #[test]
+    fn test_from_boxed_slice_locks_buffer() {
+        let slice: Box<[u8]> = vec![7; 48].into_boxed_slice();
+        let slice_ptr = slice.as_ptr();
+
+        let mut secret = SecretBox::from(slice);
+
+        assert_eq!(**secret.expose_secret(), [7; 48]);
+        let heap = secret.locked_heap.as_ref().unwrap();
+        assert_eq!((heap.ptr.cast_const(), heap.len), (slice_ptr, 48));
+
+        secret.zeroize();
+        assert_eq!(**secret.expose_secret(), [0; 48]);
+    }
+
+    #[test]
+    fn test_boxed_slice_clone_locks_buffer() {
+        let secret = SecretBox::from(vec![7u8; 16].into_boxed_slice());
+
+        let mut clone = secret.clone();
+
+        assert_heap_locked(&mut clone);
+        assert_eq!(**clone.expose_secret(), [7; 16]);
+    }
+
+    #[test]
+    fn test_into_inner_zeroizing() {
+        let slice: Box<[u8]> = vec![1, 2, 3].into_boxed_slice();
+        let slice_ptr = slice.as_ptr();
+
+        let bytes = SecretBox::from(slice).into_inner_zeroizing();
+
+        assert_eq!(bytes.as_ptr(), slice_ptr);
+        assert_eq!(**bytes, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_relocate_zeroizes_old_buffer() {
+        let mut secret = SecretBox::try_zeroed(8).unwrap();
+        secret.expose_secret_mut().fill(0xaa);
+
+        let old = secret.relocate(64).unwrap();
+
+        assert_eq!(*secret.expose_secret(), [0xaa; 8]);
+        assert_heap_locked(&mut secret);
+
+        let old_bytes = unsafe { core::slice::from_raw_parts(old.as_ptr(), old.capacity()) };
+        assert!(old_bytes.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_drop_after_growing_leaves_freed_buffer_alone() {
+        let mut secret = SecretBox::try_zeroed(256 << 10).unwrap();
+        secret.expose_secret_mut().reserve(8 << 20);
+        let calls = lock::CALLS.with(Cell::get);
+
+        drop(secret);
+
+        // The pages of the secret are unlocked, not the buffer freed by growing it.
+        assert_eq!(lock::CALLS.with(Cell::get), calls + 1);
+    }
+
+    #[test]
+    fn test_reserve_locked_after_growing_relocks() {
+        let mut secret = SecretBox::try_zeroed(16).unwrap();
+        secret.expose_secret_mut().extend_from_slice(&[1; 1 << 20]);
+        secret.truncate_locked(4);
+
+        // Fits the capacity of the buffer that was locked before growing.
+        secret.reserve_locked(8);
+
+        assert_heap_locked(&mut secret);
+        assert_eq!(*secret.expose_secret(), [0; 4]);
+    }
+
+    #[test]
+    fn test_truncate_locked_after_growing_leaves_freed_buffer_alone() {
+        let page_size = page::page_size();
+        let mut secret = SecretBox::try_zeroed(4 * page_size).unwrap();
+        secret.expose_secret_mut().reserve(64 * page_size);
+        let calls = lock::CALLS.with(Cell::get);
+
+        secret.truncate_locked(1);
+
+        assert_eq!(lock::CALLS.with(Cell::get), calls);
+        assert!(secret.locked_heap.is_none());
+    }
+
+    #[test]
+    fn test_into_inner_zeroizing_after_replacing_slice() {
+        let mut secret = SecretBox::from(vec![1u8; 32].into_boxed_slice());
+        *secret.expose_secret_mut() = vec![2u8; 64].into_boxed_slice();
+        let calls = lock::CALLS.with(Cell::get);
+
+        let bytes = secret.into_inner_zeroizing();
+
+        // Only the pages of the secret are unlocked, the first slice was freed.
+        assert_eq!(lock::CALLS.with(Cell::get), calls + 1);
+        assert_eq!(**bytes, [2; 64]);
+    }
+}
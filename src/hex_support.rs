@@ -0,0 +1,59 @@
+//! Decoding hex-encoded secrets, backed by the [`hex`] crate.
+
+use crate::{lock, ExposeSecret, SecretBox};
+use alloc::vec::Vec;
+use hex::FromHexError;
+
+impl SecretBox<Vec<u8>> {
+    /// Decode a hex string, e.g. a key loaded from a config file, into a new secret.
+    ///
+    /// The buffer is sized from the length of `s` and locked before decoding, so the
+    /// decoded bytes only ever live in locked memory. On error the bytes decoded so far
+    /// are zeroized. `s` itself is left to the caller to zeroize.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let key = SecretBox::from_hex("deadbeef").unwrap();
+    ///
+    /// assert_eq!(*key.expose_secret(), [0xde, 0xad, 0xbe, 0xef]);
+    /// ```
+    pub fn from_hex(s: &str) -> Result<Self, FromHexError> {
+        let mut secret = Self::try_zeroed(s.len() / 2).unwrap_or_else(lock::failed);
+
+        hex::decode_to_slice(s, &mut secret.expose_secret_mut())?;
+
+        Ok(secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_hex() {
+        let secret = SecretBox::from_hex("00ff7A").unwrap();
+
+        assert_eq!(*secret.expose_secret(), [0x00, 0xff, 0x7a]);
+        assert!(secret.locked_heap.is_some());
+    }
+
+    #[test]
+    fn test_from_hex_invalid() {
+        assert_eq!(
+            SecretBox::from_hex("abc").unwrap_err(),
+            FromHexError::OddLength
+        );
+        assert_eq!(
+            SecretBox::from_hex("zz").unwrap_err(),
+            FromHexError::InvalidHexCharacter { c: 'z', index: 0 }
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Keys tagged with their purpose, so keys of different purposes can't be mixed up.
+
+use crate::{ExposeSecret, LockError, SecretBox, SecretGuard, SecretGuardMut};
+use alloc::boxed::Box;
+use core::{
+    any,
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+use zeroize::Zeroize;
+
+/// Marker for what a [`SecretKey`] is used for, implemented by an uninhabited type per
+/// purpose.
+///
+/// ```
+/// use shush::KeyPurpose;
+///
+/// enum Signing {}
+///
+/// impl KeyPurpose for Signing {}
+/// ```
+pub trait KeyPurpose {}
+
+/// 256-bit key for the purpose `P`, e.g. a signing key, behaving like a
+/// `SecretBox<[u8; 32]>`.
+///
+/// Keys of different purposes are different types, so passing an encryption key where
+/// a signing key is expected fails to compile. The purpose only exists at compile
+/// time, the key is stored, exposed and zeroized exactly like a [`SecretBox`].
+///
+/// # Examples
+///
+/// ```
+/// use shush::{ExposeSecret, KeyPurpose, SecretKey};
+///
+/// enum Signing {}
+/// impl KeyPurpose for Signing {}
+///
+/// fn sign(key: &SecretKey<Signing>, message: &[u8]) -> u8 {
+///     key.expose_secret()[0] ^ message[0]
+/// }
+///
+/// let key = SecretKey::<Signing>::new(Box::new([7; 32]));
+///
+/// assert_eq!(sign(&key, b"\x01"), 6);
+/// ```
+pub struct SecretKey<P: KeyPurpose> {
+    secret: SecretBox<[u8; 32]>,
+    // `fn() -> P` keeps the key `Send` and `Sync` whatever the marker type.
+    purpose: PhantomData<fn() -> P>,
+}
+
+impl<P: KeyPurpose> SecretKey<P> {
+    /// Create a key using pre-boxed bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the key cannot be locked, see [`Self::try_new`] for a
+    /// fallible version.
+    pub fn new(boxed_key: Box<[u8; 32]>) -> Self {
+        Self::from(SecretBox::new(boxed_key))
+    }
+
+    /// Create a key using pre-boxed bytes, returning an error if its memory cannot be
+    /// locked.
+    pub fn try_new(boxed_key: Box<[u8; 32]>) -> Result<Self, LockError> {
+        SecretBox::try_new(boxed_key).map(Self::from)
+    }
+
+    /// Expose the key to `f` and return whatever it returns, see
+    /// [`SecretBox::with_secret`].
+    pub fn with_secret<R>(&self, f: impl FnOnce(&[u8; 32]) -> R) -> R {
+        self.secret.with_secret(f)
+    }
+
+    /// Drop the purpose, keeping the locked key.
+    pub fn into_secret(self) -> SecretBox<[u8; 32]> {
+        self.secret
+    }
+}
+
+impl<P: KeyPurpose> From<SecretBox<[u8; 32]>> for SecretKey<P> {
+    /// Tag an existing secret with the purpose `P`, keeping its locked allocation.
+    fn from(secret: SecretBox<[u8; 32]>) -> Self {
+        Self {
+            secret,
+            purpose: PhantomData,
+        }
+    }
+}
+
+impl<P: KeyPurpose> ExposeSecret<[u8; 32]> for SecretKey<P> {
+    fn expose_secret(&self) -> SecretGuard<'_, [u8; 32]> {
+        self.secret.expose_secret()
+    }
+
+    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, [u8; 32]> {
+        self.secret.expose_secret_mut()
+    }
+}
+
+impl<P: KeyPurpose> Zeroize for SecretKey<P> {
+    fn zeroize(&mut self) {
+        self.secret.zeroize()
+    }
+}
+
+impl<P: KeyPurpose> Clone for SecretKey<P> {
+    /// Clone the key into a newly locked allocation, see [`SecretBox::clone`].
+    fn clone(&self) -> Self {
+        Self::from(self.secret.clone())
+    }
+}
+
+impl<P: KeyPurpose> Debug for SecretKey<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey<{}>({})", any::type_name::<P>(), self.secret.debug_label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Signing {}
+
+    impl KeyPurpose for Signing {}
+
+    #[test]
+    fn test_secret_key_behaves_like_secret_box() {
+        let mut key = SecretKey::<Signing>::new(Box::new([7; 32]));
+
+        assert_eq!(*key.expose_secret(), [7; 32]);
+        key.expose_secret_mut()[0] = 8;
+        assert_eq!(key.with_secret(|key| key[0]), 8);
+
+        let clone = key.clone();
+        key.zeroize();
+
+        assert_eq!(*key.expose_secret(), [0; 32]);
+        assert_eq!(clone.expose_secret()[0], 8);
+        assert_eq!(*clone.into_secret().expose_secret(), {
+            let mut bytes = [7; 32];
+            bytes[0] = 8;
+            bytes
+        });
+    }
+
+    #[test]
+    fn test_secret_key_debug_names_purpose() {
+        let key = SecretKey::<Signing>::new(Box::new([7; 32]));
+
+        let debug = format!("{key:?}");
+
+        assert!(debug.starts_with("SecretKey<") && debug.contains("Signing"));
+        assert!(debug.ends_with("([REDACTED])"));
+    }
+}
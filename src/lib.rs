@@ -1,43 +1,298 @@
 //! This is a fork of the [secrets](https://github.com/stouset/secrets) crate.
 //! This crate adds `mlock`  to lock the secret's page in memory
+//!
+//! # Structured secrets
+//!
+//! A [`SecretBox`] zeroizes its secret by calling [`Zeroize::zeroize`] on it, so a
+//! struct holding several fields is only cleared as far as its `Zeroize` impl goes.
+//! Rather than implementing it by hand, derive it with the `derive` feature of the
+//! `zeroize` crate, which zeroizes every field in turn, including the heap buffers of
+//! `Vec`s and `String`s. Fields that aren't secret can opt out with `#[zeroize(skip)]`.
+//!
+//! ```
+//! use shush::{ExposeSecret, SecretBox};
+//! use zeroize::Zeroize;
+//!
+//! #[derive(Zeroize)]
+//! struct Credentials {
+//!     key: [u8; 32],
+//!     certificate: Vec<u8>,
+//!     #[zeroize(skip)]
+//!     key_id: u32,
+//! }
+//!
+//! let credentials = SecretBox::new(Box::new(Credentials {
+//!     key: [7; 32],
+//!     certificate: b"-----BEGIN CERTIFICATE-----".to_vec(),
+//!     key_id: 42,
+//! }));
+//!
+//! assert_eq!(credentials.expose_secret().key_id, 42);
+//! ```
+//!
+//! The derive expands to paths in `::zeroize`, so it needs `zeroize` as a direct
+//! dependency, with its `derive` feature, the re-exported [`zeroize`] module alone is
+//! not enough.
+//!
+//! # `no_std`
+//!
+//! Without the `std` feature the crate is `#![no_std]` and only needs `alloc`. Secrets,
+//! their guards and zeroizing on drop work the same, but memory is not locked. Secrets
+//! are allocated with the global allocator, so a `no_std` application chooses where they
+//! live with `#[global_allocator]`.
+//!
+//! # Features
+//!
+//! - `std` (enabled by default): APIs built on `std`, such as reading secrets from an
+//!   `io::Read`.
+//! - `mlock` (enabled by default, implies `std`): lock the memory of every secret so it
+//!   is never swapped to disk. Without it secrets are still zeroized on drop, but may end
+//!   up in swap files or hibernation images. Disable it on targets without `mlock`, such
+//!   as WASM.
+//! - `subtle`: constant-time comparison of secrets, also behind `==` for types
+//!   implementing `subtle::ConstantTimeEq`. Other secrets can't be compared with `==`.
+//! - `dontfork`: on Linux and macOS, keep the pages of every secret out of child
+//!   processes with `madvise(MADV_DONTFORK)`, or `minherit(VM_INHERIT_NONE)` on macOS.
+//!   The child loses the whole pages, including any other data sharing them, so only
+//!   enable it if forked children never touch memory allocated before the fork.
+//! - `serde`: deserialize secrets into locked memory. Serializing a secret writes
+//!   `"[REDACTED]"` instead of its value.
+//! - `serialize-plaintext`: serialize the actual value of secrets, only for trusted sinks
+//!   such as an encrypted vault file.
+//! - `rand`: generate random secrets in place from a `rand_core::RngCore`, and split
+//!   secrets into XOR shares.
+//! - `hex`: decode hex-encoded secrets straight into locked memory.
+//! - `base64`: decode base64-encoded secrets into locked memory, and encode them into
+//!   a locked [`SecretString`].
+//! - `digest`: fingerprint secrets with any `digest::Digest`, e.g. SHA-256, without
+//!   exposing them outside the hash.
+//! - `hash` (implies `std`): implement `Hash` for secrets as a SipHash digest under a
+//!   random key of the process, so they can key a `HashMap` without the map's hasher
+//!   seeing them. Without it secrets deliberately don't implement `Hash`.
+//! - `tracing`: emit `tracing` events when a secret is created, exposed and dropped,
+//!   for auditing. Events carry the type name and size of the secret, never its value,
+//!   and the hooks compile to nothing without the feature.
+//! - `registry`: track every live secret in a process-wide registry, so [`wipe_all`]
+//!   can zeroize them all, e.g. from a `SIGTERM` handler right before exiting.
+//! - `deref`: implement `Deref` directly on [`SecretBox`]. This trades the explicit,
+//!   scoped exposure of guards for ergonomics in tight loops: any `&SecretBox` can then
+//!   be read without `expose_secret`, and a protected secret stays readable for the
+//!   rest of its life once dereferenced. Off by default.
+//! - `prefault`: write-touch every page of a secret right after locking it, so it is
+//!   resident immediately on systems that only fault locked pages in lazily, at the cost
+//!   of touching large secrets upfront.
+//! - `abort-on-lock-failure` (implies `mlock`): abort the process instead of panicking
+//!   when a constructor that can't return an error fails to lock a secret, and when
+//!   unlocking fails, so nothing unwinds past a secret that isn't locked. The `try_`
+//!   constructors still return errors.
+//! - `secrecy-compat`: implement `secrecy::ExposeSecret` for [`SecretBox`], to pass
+//!   secrets to libraries built on the `secrecy` crate, and convert between
+//!   `secrecy::SecretString` and [`SecretString`], copying the string into locked
+//!   memory on the way in.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 #![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
 
+extern crate alloc;
+
+use alloc::{
+    alloc::{alloc_zeroed, handle_alloc_error, Layout},
+    boxed::Box,
+    string::String,
+    vec::Vec,
+};
+use core::mem::{self, align_of, size_of, size_of_val, ManuallyDrop, MaybeUninit};
+use core::ops::{Deref, DerefMut, Index, IndexMut};
+use core::ptr::{self, NonNull};
 use core::{
     any,
+    array::TryFromSliceError,
     fmt::{self, Debug},
+    pin::Pin,
 };
-use memsec::{mlock, munlock};
-use std::ops::{Deref, DerefMut};
-use std::mem::size_of_val;
+use heap::LockedHeap;
+use page::{Pages, Prot};
+use pool::Slot;
+use protect::Protection;
+use registry::Registration;
+#[cfg(feature = "subtle")]
+pub use subtle;
 pub use zeroize;
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+mod advise;
+mod arc;
+mod arena;
+#[cfg(feature = "base64")]
+mod base64_support;
+#[cfg(feature = "std")]
+mod budget;
+mod buffer;
+mod builder;
+mod bytes;
+mod cell;
+#[cfg(feature = "subtle")]
+mod ct;
+mod erased;
+mod error;
+#[cfg(feature = "digest")]
+mod digest_support;
+#[cfg(feature = "std")]
+mod entropy;
+#[cfg(feature = "rand")]
+mod generate;
+#[cfg(feature = "hash")]
+mod hash_support;
+mod heap;
+mod key;
+mod limited;
+#[cfg(feature = "hex")]
+mod hex_support;
+mod lock;
+mod map;
+mod page;
+mod pool;
+mod protect;
+mod registry;
+#[cfg(feature = "secrecy-compat")]
+mod secrecy_compat;
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(feature = "rand")]
+mod share;
+mod string;
+mod trace;
+mod vec;
+
+pub use arc::{SecretArc, SecretArcSwap};
+pub use arena::SecretArena;
+#[cfg(feature = "std")]
+pub use budget::{memlock_budget, MemlockBudget};
+pub use buffer::SecretBuffer;
+pub use builder::SecretBoxBuilder;
+pub use bytes::SecretBytes;
+pub use cell::{SecretCell, SecretRef, SecretRefMut};
+pub use erased::AnySecret;
+pub use error::{LockError, SizeError};
+pub use key::{KeyPurpose, SecretKey};
+pub use limited::{LimitedGuard, LimitedSecret};
+pub use map::SecretMap;
+pub use pool::SecretPool;
+#[cfg(feature = "registry")]
+pub use registry::wipe_all;
+pub use string::SecretString;
+pub use vec::SecretVec;
 
 /// Wrapper for the inner secret. Can be exposed by [`ExposeSecret`]
+///
+/// Every secret is moved into page-aligned pages of its own, taking at least a whole
+/// page of locked memory. Locks apply to whole pages and don't nest, so two secrets
+/// sharing a page would unlock each other: dropping one must never unlock the memory
+/// of another. Heap buffers owned by the secret, e.g. the bytes of a `Vec<u8>`, come
+/// from the global allocator and may still share pages. Secrets created in a
+/// [`SecretPool`] or a [`SecretArena`] share its pages instead, which stay locked as
+/// long as it does, and [`SecretBox::from_raw_locked`] locks a secret where it already
+/// is.
+///
+/// On drop the secret is zeroized while its memory is still locked, and only then
+/// unlocked. If unlocking fails, or is skipped for a pooled secret, the bytes that may
+/// end up swapped out are already zeroes.
 pub struct SecretBox<S: Zeroize> {
-    inner_secret: Box<S>,
+    inner_secret: NonNull<S>,
+    /// Pages of their own holding the secret, see [`Protection`].
+    protection: Option<Protection>,
+    /// Slot of a pool or an arena holding the secret instead, see [`SecretPool`] and
+    /// [`SecretArena`].
+    slot: Option<Slot>,
+    /// Heap buffer owned by the secret that is locked as well, see [`heap`].
+    locked_heap: Option<LockedHeap<S>>,
+    /// Printed by `Debug` in place of the secret.
+    debug_label: &'static str,
+    /// Whether the memory of the secret is locked, see [`SecretBox::is_locked`].
+    locked: bool,
+    /// Whether the pages of the secret are locked on fault, see
+    /// [`SecretBox::new_on_fault`].
+    on_fault: bool,
+    /// Set by [`SecretBox::destroy`], the secret is zeroized and unlocked already.
+    destroyed: bool,
+    /// Set by [`SecretBox::zeroized_by_drop`], dropping the secret zeroizes it.
+    zeroized_by_drop: bool,
+    /// Entry of the secret in the process-wide registry, see [`registry`].
+    registration: Registration,
+}
+
+/// Default [`SecretBox::with_debug_label`].
+const REDACTED: &str = "[REDACTED]";
+
+/// Keep the locked memory of a new secret out of core dumps and forks.
+///
+/// This is best-effort, the secret is still locked if it fails.
+unsafe fn advise_locked(secret_ptr: *mut u8, len: usize) {
+    let _ = advise::exclude_from_dump(secret_ptr, len);
+    let _ = advise::exclude_from_fork(secret_ptr, len);
+}
+
+/// Revert [`advise_locked`] and unlock memory of a secret about to be freed.
+///
+/// Failures are deliberately ignored: this runs on drop, where panicking could abort
+/// the process while unwinding, and the secret is zeroized either way. With the
+/// `abort-on-lock-failure` feature failing to unlock aborts the process instead.
+unsafe fn release_locked(secret_ptr: *mut u8, len: usize) {
+    let _ = advise::include_in_fork(secret_ptr, len);
+    let _ = advise::include_in_dump(secret_ptr, len);
+
+    #[cfg(feature = "abort-on-lock-failure")]
+    if !lock::unlock(secret_ptr, len) {
+        std::eprintln!("Unable to munlock variable");
+        std::process::abort();
+    }
+
+    #[cfg(not(feature = "abort-on-lock-failure"))]
+    let _ = lock::unlock(secret_ptr, len);
 }
 
+// `SecretBox` owns its secret exclusively, like a `Box<S>`.
+unsafe impl<S: Zeroize + Send> Send for SecretBox<S> {}
+unsafe impl<S: Zeroize + Sync> Sync for SecretBox<S> {}
+
 impl<S: Zeroize> Zeroize for SecretBox<S> {
     fn zeroize(&mut self) {
-        self.inner_secret.as_mut().zeroize()
+        if !self.destroyed {
+            self.expose_secret_mut().zeroize()
+        }
     }
 }
 
 impl<S: Zeroize> Drop for SecretBox<S> {
     fn drop(&mut self) {
-        let len = size_of_val(&*self.inner_secret);
+        trace::dropped(self);
+        registry::unregister(self);
+
+        if let Some(protection) = &self.protection {
+            unsafe { protection.pages().protect(Prot::ReadWrite) };
+        }
 
-        let secret_ptr = self.inner_secret.as_ref() as *const S;
+        let secret_ptr = self.inner_secret.as_ptr();
 
         unsafe {
-            if !munlock(secret_ptr as *mut u8, len) {
-                panic!("Unable to munlock variable")
+            if self.destroyed {
+                ptr::drop_in_place(secret_ptr);
+            } else if self.zeroized_by_drop && self.locked_heap.is_none() {
+                // The secret zeroizes itself as it is dropped, while it is still locked.
+                // A locked heap buffer is freed by the drop, so it is wiped first below.
+                ptr::drop_in_place(secret_ptr);
+                self.release();
+            } else {
+                self.wipe();
+                ptr::drop_in_place(secret_ptr);
             }
-        }
 
-        self.zeroize()
+            // Otherwise the memory itself is freed when the protection or slot is dropped.
+            if self.protection.is_none() && self.slot.is_none() {
+                drop(Box::from_raw(secret_ptr.cast::<MaybeUninit<S>>()));
+            }
+        }
     }
 }
 
@@ -51,237 +306,2629 @@ impl<S: Zeroize> From<Box<S>> for SecretBox<S> {
 
 impl<S: Zeroize> SecretBox<S> {
     /// Create a secret value using a pre-boxed value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see [`Self::try_new`]
+    /// for a fallible version.
     pub fn new(boxed_secret: Box<S>) -> Self {
-        let len = size_of_val(&*boxed_secret);
+        Self::try_new(boxed_secret).unwrap_or_else(lock::failed)
+    }
 
-        let secret_ptr = Box::into_raw(boxed_secret);
+    /// Create a secret value using a pre-boxed value, returning an error if its
+    /// memory cannot be locked.
+    ///
+    /// The secret is moved into pages of its own, see [`SecretBox`]. On failure the
+    /// secret is zeroized before the error is returned.
+    pub fn try_new(boxed_secret: Box<S>) -> Result<Self, LockError> {
+        let pages = Pages::alloc(size_of::<S>());
 
-        unsafe {
-            if !mlock(secret_ptr as *mut u8, len) {
-                panic!("Unable to mlock variable ")
-            }
+        Self::try_new_in_pages(boxed_secret, pages, 0, Protection::unprotected)
+    }
+
+    /// Same as [`Self::new`], but returns an error if the secret takes more than `MAX`
+    /// bytes, to catch a mistake such as wrapping a huge array early instead of using
+    /// up `RLIMIT_MEMLOCK`.
+    ///
+    /// Only the memory of `S` itself counts, as given by [`size_of_val`], not heap
+    /// buffers it points to. If it is too large the secret is zeroized before the error
+    /// is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// assert!(SecretBox::new_bounded::<64>(Box::new([0u8; 32])).is_ok());
+    ///
+    /// let err = SecretBox::new_bounded::<64>(Box::new([0u8; 4096])).unwrap_err();
+    /// assert_eq!((err.size(), err.max()), (4096, 64));
+    /// ```
+    pub fn new_bounded<const MAX: usize>(mut boxed_secret: Box<S>) -> Result<Self, SizeError> {
+        let size = size_of_val(&*boxed_secret);
+
+        if size > MAX {
+            boxed_secret.zeroize();
+            return Err(SizeError { size, max: MAX });
         }
 
-        let inner_secret = unsafe { Box::from_raw(secret_ptr) };
+        Ok(Self::new(boxed_secret))
+    }
 
-        Self { inner_secret }
+    /// Create a secret value whose memory is inaccessible while it is not exposed.
+    ///
+    /// The secret is moved into pages of its own, which are only readable while a
+    /// [`SecretGuard`] is alive and writable while a [`SecretGuardMut`] is alive, so a
+    /// stray read through a dangling pointer faults instead of leaking the secret.
+    ///
+    /// Like every secret it takes at least a whole page of locked memory, and exposing
+    /// it costs an `mprotect` call. Only the memory of `S` itself is protected, not heap
+    /// buffers it points to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see
+    /// [`Self::try_new_protected`] for a fallible version.
+    pub fn new_protected(boxed_secret: Box<S>) -> Self {
+        Self::try_new_protected(boxed_secret).unwrap_or_else(lock::failed)
     }
-}
 
-impl<S: Zeroize + Default> SecretBox<S> {
-    /// Create a secret value using a function that can initialize the vale in-place.
-    pub fn new_with_mut(ctr: impl FnOnce(&mut S)) -> Self {
-        let mut secret = Self::default();
-        ctr(&mut *secret.expose_secret_mut());
-        secret
+    /// Same as [`Self::new_protected`], but returns an error if the memory of the secret
+    /// cannot be locked.
+    ///
+    /// On failure the secret is zeroized before the error is returned.
+    pub fn try_new_protected(boxed_secret: Box<S>) -> Result<Self, LockError> {
+        let pages = Pages::alloc(size_of::<S>());
+
+        Self::try_new_in_pages(boxed_secret, pages, 0, Protection::new)
     }
-}
 
-impl<S: Zeroize + Clone> SecretBox<S> {
-    /// Create a secret value using the provided function as a constructor.
+    /// Create a secret value between two inaccessible guard pages, so overrunning it
+    /// faults instead of reading or corrupting neighbouring memory.
     ///
-    /// The implementation makes an effort to zeroize the locally constructed value
-    /// before it is copied to the heap, and constructing it inside the closure minimizes
-    /// the possibility of it being accidentally copied by other code.
+    /// The secret is placed at the end of its pages, right before the trailing guard
+    /// page, so the first byte past it faults. Unlike [`Self::new_protected`] the secret
+    /// itself stays accessible. It takes at least a whole page of locked memory, plus
+    /// two unlocked guard pages.
     ///
-    /// **Note:** using [`Self::new`] or [`Self::new_with_mut`] is preferable when possible,
-    /// since this method's safety relies on empyric evidence and may be violated on some targets.
-    pub fn new_with_ctr(ctr: impl FnOnce() -> S) -> Self {
-        let mut data = ctr();
-        let secret = Self::new(Box::new(data.clone()));
-        data.zeroize();
-        secret
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see
+    /// [`Self::try_new_guarded`] for a fallible version.
+    pub fn new_guarded(boxed_secret: Box<S>) -> Self {
+        Self::try_new_guarded(boxed_secret).unwrap_or_else(lock::failed)
     }
 
-    /// Same as [`Self::new_with_ctr`], but the constructor can be fallible.
+    /// Same as [`Self::new_guarded`], but returns an error if the memory of the secret
+    /// cannot be locked.
     ///
-    ///
-    /// **Note:** using [`Self::new`] or [`Self::new_with_mut`] is preferable when possible,
-    /// since this method's safety relies on empyric evidence and may be violated on some targets.
-    pub fn try_new_with_ctr<E>(ctr: impl FnOnce() -> Result<S, E>) -> Result<Self, E> {
-        let mut data = ctr()?;
-        let secret = Self::new(Box::new(data.clone()));
-        data.zeroize();
-        Ok(secret)
+    /// On failure the secret is zeroized before the error is returned.
+    pub fn try_new_guarded(boxed_secret: Box<S>) -> Result<Self, LockError> {
+        let pages = Pages::alloc_guarded(size_of::<S>());
+        // The size of a type is a multiple of its alignment, so this stays aligned.
+        let offset = pages.len() - size_of::<S>();
+
+        Self::try_new_in_pages(boxed_secret, pages, offset, Protection::unprotected)
     }
-}
 
-impl<S: Zeroize + Default> Default for SecretBox<S> {
-    fn default() -> Self {
-        let inner_secret = Box::<S>::default();
-        SecretBox::new(inner_secret)
+    /// Create a secret value backed by huge pages, e.g. a multi-megabyte key table, so
+    /// it takes fewer pages to lock and fewer TLB entries to access.
+    ///
+    /// The secret is moved into huge pages mapped with `mmap(MAP_HUGETLB)` on Linux,
+    /// which need huge pages reserved through `vm.nr_hugepages`. Where none are
+    /// available, and on other platforms, the secret is moved into normal pages like
+    /// with [`Self::new`]. The secret takes at least one whole huge page, so it only
+    /// pays off for secrets about that large. Clones are backed by huge pages as well.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see
+    /// [`Self::try_new_hugepages`] for a fallible version.
+    pub fn new_hugepages(boxed_secret: Box<S>) -> Self {
+        Self::try_new_hugepages(boxed_secret).unwrap_or_else(lock::failed)
     }
-}
 
-impl<S: Zeroize> Debug for SecretBox<S> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "SecretBox<{}>([REDACTED])", any::type_name::<S>())
+    /// Same as [`Self::new_hugepages`], but returns an error if the memory of the secret
+    /// cannot be locked.
+    ///
+    /// On failure the secret is zeroized before the error is returned.
+    pub fn try_new_hugepages(boxed_secret: Box<S>) -> Result<Self, LockError> {
+        let pages = Pages::alloc_huge(size_of::<S>())
+            .unwrap_or_else(|| Pages::alloc(size_of::<S>()));
+
+        Self::try_new_in_pages(boxed_secret, pages, 0, Protection::unprotected)
     }
-}
 
-impl<S> Clone for SecretBox<S>
-where
-    S: CloneableSecret,
-{
-    fn clone(&self) -> Self {
-        SecretBox::new(self.inner_secret.clone())
+    /// Same as [`Self::new`], but the pages of the secret are only locked once they are
+    /// touched, using `mlock2(MLOCK_ONFAULT)` on Linux, so pages that are never accessed
+    /// don't take up locked memory.
+    ///
+    /// Kernels before Linux 4.4 and other platforms don't support locking on fault,
+    /// there the pages are locked upfront like with [`Self::new`]. The `prefault`
+    /// feature doesn't apply to secrets locked on fault.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see
+    /// [`Self::try_new_on_fault`] for a fallible version.
+    pub fn new_on_fault(boxed_secret: Box<S>) -> Self {
+        Self::try_new_on_fault(boxed_secret).unwrap_or_else(lock::failed)
     }
-}
 
-impl<S: Zeroize> ExposeSecret<S> for SecretBox<S> {
-    fn expose_secret(&mut self) -> SecretGuard<'_, S> {
-        SecretGuard::new(&self.inner_secret)
+    /// Same as [`Self::new_on_fault`], but returns an error if the memory of the secret
+    /// cannot be locked.
+    ///
+    /// On failure the secret is zeroized before the error is returned.
+    pub fn try_new_on_fault(boxed_secret: Box<S>) -> Result<Self, LockError> {
+        let pages = Pages::alloc(size_of::<S>());
+
+        Self::try_new_locked_with(boxed_secret, pages, 0, Protection::unprotected, true)
     }
 
-    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, S> {
-        SecretGuardMut::new(&mut self.inner_secret)
+    /// Lock `pages` and move the secret to `offset` bytes into them.
+    ///
+    /// On failure the secret is zeroized before the error is returned.
+    fn try_new_in_pages(
+        boxed_secret: Box<S>,
+        pages: Pages,
+        offset: usize,
+        protection: fn(Pages) -> Protection,
+    ) -> Result<Self, LockError> {
+        Self::try_new_locked_with(boxed_secret, pages, offset, protection, false)
     }
-}
 
-/// Secret Guard that holds a reference to the secret.
-pub struct SecretGuard<'a, S>
-where
-    S: Zeroize,
-{
-    data: &'a S,
-}
+    /// Same as [`Self::try_new_in_pages`], locking the pages on fault if `on_fault`.
+    fn try_new_locked_with(
+        mut boxed_secret: Box<S>,
+        pages: Pages,
+        offset: usize,
+        protection: fn(Pages) -> Protection,
+        on_fault: bool,
+    ) -> Result<Self, LockError> {
+        assert!(align_of::<S>() <= page::page_size(), "secret is aligned past a page");
 
-impl<S> Deref for SecretGuard<'_, S>
-where
-    S: Zeroize,
-{
-    type Target = S;
+        let lock = match on_fault {
+            true => lock::lock_on_fault,
+            false => lock::lock,
+        };
 
-    fn deref(&self) -> &Self::Target {
-        self.data
-    }
-}
+        if let Err(err) = unsafe { lock(pages.as_ptr(), pages.len()) } {
+            boxed_secret.zeroize();
+            return Err(err);
+        }
 
-/// Secret Guard that holds a mutable to reference to the secret.
-pub struct SecretGuardMut<'a, S>
-where
-    S: Zeroize,
-{
-    data: &'a mut S,
-}
+        unsafe { advise_locked(pages.as_ptr(), pages.len()) };
 
-impl<S> Deref for SecretGuardMut<'_, S>
-where
-    S: Zeroize,
-{
-    type Target = S;
+        let secret_ptr = unsafe { pages.as_ptr().add(offset) }.cast::<S>();
+        unsafe { Self::move_boxed(boxed_secret, secret_ptr) };
 
-    fn deref(&self) -> &Self::Target {
-        self.data
+        let mut secret = Self {
+            inner_secret: unsafe { NonNull::new_unchecked(secret_ptr) },
+            protection: Some(protection(pages)),
+            slot: None,
+            locked_heap: None,
+            debug_label: REDACTED,
+            locked: lock::ENABLED,
+            on_fault,
+            destroyed: false,
+            zeroized_by_drop: false,
+            registration: Registration::NONE,
+        };
+        registry::register(&mut secret);
+        trace::created(&secret);
+
+        Ok(secret)
     }
-}
 
-impl<S> DerefMut for SecretGuardMut<'_, S>
-where
-    S: Zeroize,
-{
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self.data
+    /// Move the secret out of `boxed_secret` to `secret_ptr`, then wipe the bytes it was
+    /// moved out of and free them without dropping the secret a second time.
+    ///
+    /// # Safety
+    ///
+    /// `secret_ptr` must be valid for writes and aligned for `S`.
+    unsafe fn move_boxed(boxed_secret: Box<S>, secret_ptr: *mut S) {
+        let source = Box::into_raw(boxed_secret);
+        ptr::copy_nonoverlapping(source, secret_ptr, 1);
+
+        Box::from_raw(source.cast::<MaybeUninit<S>>()).zeroize();
     }
-}
 
-impl<'a, S: Zeroize> SecretGuard<'a, S> {
-    /// Create a new SecretGuard instance.
-    pub fn new(data: &'a S) -> Self {
-        Self { data }
+    /// Create a secret value in a slot of `pool`, sparing the `mlock` and `munlock`
+    /// calls of a secret with pages of its own.
+    ///
+    /// If the secret doesn't fit a slot, or every slot is taken, it gets pages of its
+    /// own like with [`Self::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret is not pooled and its memory cannot be locked, see
+    /// [`Self::try_new_in`] for a fallible version.
+    pub fn new_in(boxed_secret: Box<S>, pool: &SecretPool) -> Self {
+        Self::try_new_in(boxed_secret, pool).unwrap_or_else(lock::failed)
     }
-}
 
-impl<'a, S: Zeroize> SecretGuardMut<'a, S> {
-    /// Create a new SecretGuard instance.
-    pub fn new(data: &'a mut S) -> Self {
-        Self { data }
+    /// Same as [`Self::new_in`], but returns an error if the secret is not pooled and
+    /// its memory cannot be locked.
+    pub fn try_new_in(boxed_secret: Box<S>, pool: &SecretPool) -> Result<Self, LockError> {
+        match pool.claim(size_of::<S>(), align_of::<S>()) {
+            Some(slot) => Ok(Self::new_in_slot(boxed_secret, Slot::Pool(slot))),
+            None => Self::try_new(boxed_secret),
+        }
     }
-}
 
-/// Marker trait for secrets which are allowed to be cloned
-pub trait CloneableSecret: Clone + Zeroize {}
+    /// Create a secret value in the next free bytes of `arena`, sparing the `mlock` and
+    /// `munlock` calls of a secret with pages of its own.
+    ///
+    /// If the arena has no room left for the secret, it gets pages of its own like with
+    /// [`Self::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret doesn't fit the arena and its memory cannot be locked, see
+    /// [`Self::try_new_in_arena`] for a fallible version.
+    pub fn new_in_arena(boxed_secret: Box<S>, arena: &SecretArena) -> Self {
+        Self::try_new_in_arena(boxed_secret, arena).unwrap_or_else(lock::failed)
+    }
 
-/// Create a SecretGuard that holds a reference to the secret
-pub trait ExposeSecret<S: Zeroize> {
-    /// Expose secret as non-mutable.
-    fn expose_secret(&mut self) -> SecretGuard<'_, S>;
+    /// Same as [`Self::new_in_arena`], but returns an error if the secret doesn't fit
+    /// the arena and its memory cannot be locked.
+    pub fn try_new_in_arena(boxed_secret: Box<S>, arena: &SecretArena) -> Result<Self, LockError> {
+        match arena.claim(size_of::<S>(), align_of::<S>()) {
+            Some(slot) => Ok(Self::new_in_slot(boxed_secret, Slot::Arena(slot))),
+            None => Self::try_new(boxed_secret),
+        }
+    }
 
-    /// Expose secret as mutable.
-    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, S>;
-}
+    /// Move the secret into `slot`, which is locked already.
+    fn new_in_slot(boxed_secret: Box<S>, slot: Slot) -> Self {
+        let secret_ptr = slot.as_ptr().cast::<S>();
+        unsafe { Self::move_boxed(boxed_secret, secret_ptr) };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let mut secret = Self {
+            inner_secret: unsafe { NonNull::new_unchecked(secret_ptr) },
+            protection: None,
+            slot: Some(slot),
+            locked_heap: None,
+            debug_label: REDACTED,
+            locked: lock::ENABLED,
+            on_fault: false,
+            destroyed: false,
+            zeroized_by_drop: false,
+            registration: Registration::NONE,
+        };
+        registry::register(&mut secret);
+        trace::created(&secret);
 
-    #[derive(Debug, Clone, Default)]
-    struct TestSecret {
-        data: Vec<u8>,
+        secret
     }
 
-    impl TestSecret {
-        fn new(size: usize) -> Self {
-            let mut data = vec![0; size];
-            data[0] = 1;
-            Self { data }
-        }
+    /// Take ownership of a secret already on the heap, e.g. handed over from FFI, and
+    /// lock it where it is instead of moving it into pages of its own.
+    ///
+    /// Unlike the other constructors the secret may share its pages with unrelated
+    /// allocations. Locks don't nest, so dropping the secret unlocks those pages for
+    /// anything else on them too, see [`SecretBox`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`Box::into_raw`] for a `Box<S>`, or otherwise
+    /// point to an initialized `S` allocated by the global allocator with the layout of
+    /// `S`. Ownership moves to the secret: nothing else may access or free the value
+    /// afterwards, and the secret frees it with the global allocator once dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked, see
+    /// [`Self::try_from_raw_locked`] for a fallible version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let raw = Box::into_raw(Box::new([7u8; 32]));
+    /// let key = unsafe { SecretBox::from_raw_locked(raw) };
+    ///
+    /// assert_eq!(key.expose_secret().as_ptr(), raw.cast());
+    /// ```
+    pub unsafe fn from_raw_locked(ptr: *mut S) -> Self {
+        Self::try_from_raw_locked(ptr).unwrap_or_else(lock::failed)
+    }
 
-        fn check_non_zero(&self) -> bool {
-            self.data.iter().any(|&x| x != 0)
-        }
+    /// Same as [`Self::from_raw_locked`], but returns an error if the memory of the
+    /// secret cannot be locked, after zeroizing and freeing the secret.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Self::from_raw_locked`], ownership moves to this call even on error.
+    pub unsafe fn try_from_raw_locked(ptr: *mut S) -> Result<Self, LockError> {
+        let mut boxed_secret = Box::from_raw(ptr);
 
-        fn check_zero(&self) -> bool {
-            self.data.iter().all(|&x| x == 0)
+        if let Err(err) = lock::lock(ptr.cast(), size_of::<S>()) {
+            boxed_secret.zeroize();
+            return Err(err);
         }
-    }
 
-    impl Zeroize for TestSecret {
-        fn zeroize(&mut self) {
-            self.data = Vec::new();
-        }
+        advise_locked(ptr.cast(), size_of::<S>());
+
+        let mut secret = Self {
+            inner_secret: NonNull::from(Box::leak(boxed_secret)),
+            protection: None,
+            slot: None,
+            locked_heap: None,
+            debug_label: REDACTED,
+            locked: lock::ENABLED,
+            on_fault: false,
+            destroyed: false,
+            zeroized_by_drop: false,
+            registration: Registration::NONE,
+        };
+        registry::register(&mut secret);
+        trace::created(&secret);
+
+        Ok(secret)
     }
 
-    #[test]
-    fn test_secret_box_drop_zeroizes() {
-        let secret = Box::new(TestSecret::new(10));
-        let mut secret_box = SecretBox::new(secret);
-        assert!((*secret_box.expose_secret()).check_non_zero());
+    /// Hand the secret over to foreign code, e.g. a C library keeping a key, returning a
+    /// pointer to it and its length in bytes.
+    ///
+    /// The secret stays locked but is neither zeroized nor unlocked: responsibility for
+    /// it moves to the caller, who must give it back with [`Self::reclaim`] to have it
+    /// wiped and freed. Until then it leaks, like with [`Box::leak`].
+    ///
+    /// The secret is left where it is if it lives in pages of its own, as with
+    /// [`Self::new`]. A protected, guarded, huge-page, pooled or arena secret is first
+    /// moved to such pages, since foreign code can't honor guards or return a slot,
+    /// so the pointer is always readable and writable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the secret was destroyed, if it has a locked heap buffer, which would
+    /// be freed without being unlocked, or if the pages it is moved to cannot be
+    /// locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let (ptr, len) = SecretBox::new(Box::new([7u8; 32])).leak();
+    /// // e.g. `unsafe { ffi::set_key(ptr.cast(), len) }`
+    /// unsafe { *ptr = [8; 32] };
+    ///
+    /// let key = unsafe { SecretBox::reclaim(ptr, len) };
+    /// assert_eq!(*key.expose_secret(), [8; 32]);
+    /// ```
+    pub fn leak(self) -> (*mut S, usize) {
+        self.assert_alive();
+        assert!(self.locked_heap.is_none(), "cannot leak a secret with a locked heap buffer");
 
-        drop(secret_box);
+        let in_place = self.protection.as_ref().is_some_and(|protection| {
+            let pages = protection.pages();
 
-        // Verify that secret is zeroized after drop
-        // This requires checking the memory, which is not straightforward in Rust.
-        // Here we rely on the zeroize trait to ensure it zeroizes.
-        assert!(TestSecret::default().check_zero());
-    }
+            !protection.is_protected()
+                && !pages.is_guarded()
+                && !pages.is_huge()
+                && pages.as_ptr() == self.inner_secret.as_ptr().cast()
+        });
 
-    #[test]
-    fn test_secret_box_expose_secret_mut() {
-        let secret = Box::new(TestSecret::new(10));
-        let mut secret_box = SecretBox::new(secret);
-        {
-            let mut exposed = secret_box.expose_secret_mut();
-            (*exposed).data[0] = 42;
+        // Locked before taking over the drop, so the secret is wiped if this panics.
+        let pages = (!in_place).then(|| {
+            let pages = Pages::alloc(size_of::<S>());
+            unsafe { lock::lock(pages.as_ptr(), pages.len()) }.unwrap_or_else(lock::failed);
+            pages
+        });
+
+        let mut this = ManuallyDrop::new(self);
+        let secret_ptr = this.inner_secret.as_ptr();
+        registry::unregister(&mut this);
+
+        let Some(pages) = pages else {
+            // Forgetting the protection forgets its pages, `reclaim` takes them back.
+            mem::forget(unsafe { ptr::read(&this.protection) });
+            return (secret_ptr, size_of::<S>());
+        };
+
+        unsafe {
+            advise_locked(pages.as_ptr(), pages.len());
+
+            if let Some(protection) = &this.protection {
+                protection.pages().protect(Prot::ReadWrite);
+            }
+
+            let leaked_ptr = pages.as_ptr().cast::<S>();
+            ptr::copy_nonoverlapping(secret_ptr, leaked_ptr, 1);
+
+            // The secret was moved out, so only its bytes are wiped, then its old
+            // memory is unlocked and freed without dropping it.
+            (*secret_ptr.cast::<MaybeUninit<S>>()).zeroize();
+            this.release();
+
+            let (protection, slot) = (ptr::read(&this.protection), ptr::read(&this.slot));
+            if protection.is_none() && slot.is_none() {
+                drop(Box::from_raw(secret_ptr.cast::<MaybeUninit<S>>()));
+            }
+            drop((protection, slot));
+
+            mem::forget(pages);
+            (leaked_ptr, size_of::<S>())
         }
+    }
 
-        assert_eq!((*secret_box.expose_secret()).data[0], 42);
+    /// Take back a secret handed over with [`Self::leak`], to have it zeroized, unlocked
+    /// and freed once dropped.
+    ///
+    /// The secret comes back as if created with [`Self::new`], without a debug label.
+    /// Its memory is still locked, so nothing is locked anew.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` and `len` must have been returned by [`Self::leak`] on a `SecretBox<S>`,
+    /// and the secret must not have been reclaimed yet. Ownership moves back to the
+    /// secret: foreign code may not access it afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is not the size of `S`, which can't have come from
+    /// [`Self::leak`].
+    pub unsafe fn reclaim(ptr: *mut S, len: usize) -> Self {
+        assert_eq!(len, size_of::<S>(), "length of leaked secret doesn't match its type");
+
+        let mut secret = Self {
+            inner_secret: NonNull::new_unchecked(ptr),
+            protection: Some(Protection::unprotected(Pages::from_raw(ptr.cast(), len))),
+            slot: None,
+            locked_heap: None,
+            debug_label: REDACTED,
+            locked: lock::ENABLED,
+            on_fault: false,
+            destroyed: false,
+            zeroized_by_drop: false,
+            registration: Registration::NONE,
+        };
+        registry::register(&mut secret);
+        trace::created(&secret);
+
+        secret
     }
 
-    #[test]
-    fn test_secret_box_new_with_ctr() {
-        let mut secret_box = SecretBox::new_with_ctr(|| TestSecret::new(10));
-        assert!((*secret_box.expose_secret()).check_non_zero());
+    /// Zeroize the secret, then unlock its memory, including its heap buffer.
+    ///
+    /// Zeroizing first means only zeroes are left in memory that may be swapped out
+    /// once unlocked.
+    ///
+    /// # Safety
+    ///
+    /// The pages of a protected secret must be writable, and the secret must not be
+    /// wiped already.
+    unsafe fn wipe(&mut self) {
+        self.inner_secret.as_mut().zeroize();
+        self.release();
+    }
+
+    /// Unlock the memory of the secret, including its heap buffer.
+    ///
+    /// # Safety
+    ///
+    /// The secret must be zeroized already, and not released yet. The pages of a
+    /// protected secret with a locked heap buffer must be readable.
+    unsafe fn release(&mut self) {
+        let (secret_ptr, len) = self.locked_region();
+
+        if let Some(heap) = self.locked_heap.take() {
+            // A buffer that moved since it was locked was freed by its owner, its pages
+            // may belong to other allocations by now.
+            if (heap.buffer)(self.inner_secret.as_mut()) == (heap.ptr, heap.len) {
+                release_locked(heap.ptr, heap.len);
+            }
+        }
+
+        // A pooled secret stays locked along with the rest of its pool.
+        if self.slot.is_none() {
+            release_locked(secret_ptr, len);
+        }
+    }
+
+    /// Zeroize and unlock the secret now, instead of when it is dropped.
+    ///
+    /// Exposing the secret afterwards panics rather than returning zeroed data, and
+    /// dropping it only frees its memory. Destroying it again does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// let mut session_key = SecretBox::new(Box::new([1u8; 32]));
+    /// session_key.destroy();
+    ///
+    /// assert!(session_key.is_destroyed());
+    /// ```
+    pub fn destroy(&mut self) {
+        if self.destroyed {
+            return;
+        }
+
+        if let Some(protection) = &self.protection {
+            protection.expose(Prot::ReadWrite);
+        }
+
+        unsafe { self.wipe() };
+
+        if let Some(protection) = &self.protection {
+            protection.conceal();
+        }
+
+        self.destroyed = true;
+        self.locked = false;
+    }
+
+    /// Zeroize the secret but keep its memory locked and the box usable, e.g. to reuse a
+    /// long-lived buffer for the next secret.
+    ///
+    /// Unlike [`Self::destroy`] nothing is unlocked, so the secret can be written again
+    /// through [`ExposeSecret::expose_secret_mut`] without locking anything anew. A
+    /// `Vec<u8>` is emptied but keeps its locked capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let mut buffer = SecretBox::from_slice_zeroizing(&mut [1; 32]);
+    ///
+    /// buffer.clear();
+    /// assert!(buffer.expose_secret().is_empty());
+    ///
+    /// buffer.expose_secret_mut().extend_from_slice(&[2; 32]);
+    /// assert_eq!(*buffer.expose_secret(), [2; 32]);
+    /// ```
+    pub fn clear(&mut self) {
+        self.zeroize();
+    }
+
+    /// Whether the secret was destroyed with [`Self::destroy`].
+    pub fn is_destroyed(&self) -> bool {
+        self.destroyed
+    }
+
+    /// Panic if the secret was destroyed, before handing out a reference to it.
+    fn assert_alive(&self) {
+        assert!(!self.destroyed, "secret was destroyed");
+    }
+
+    /// Memory locked for the secret.
+    fn locked_region(&self) -> (*mut u8, usize) {
+        match &self.protection {
+            Some(protection) => (protection.pages().as_ptr(), protection.pages().len()),
+            None => (self.inner_secret.as_ptr().cast(), size_of::<S>()),
+        }
+    }
+
+    /// Expose the secret to `f` and return whatever it returns.
+    ///
+    /// The secret is only reachable for the duration of the closure, which makes the
+    /// exposure window explicit compared to holding a [`SecretGuard`].
+    pub fn with_secret<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        f(&self.expose_secret())
+    }
+
+    /// Expose the secret to the async closure `f` until the future it returns completes,
+    /// and return its output.
+    ///
+    /// Prefer this over holding a [`SecretGuard`] across an `.await` by hand: the
+    /// exposure is scoped to `f`, and if the returned future is dropped before it
+    /// completes, e.g. because it was cancelled, the guard is dropped with it so a
+    /// protected secret becomes inaccessible again. The secret still stays exposed for
+    /// every `.await` inside `f`, which may take arbitrarily long, so only await what
+    /// actually needs the secret and copy nothing out of it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// async fn send(token: &[u8; 4]) -> usize {
+    ///     token.len()
+    /// }
+    ///
+    /// async fn authenticate(token: &SecretBox<[u8; 4]>) -> usize {
+    ///     token.with_secret_async(async |token| send(token).await).await
+    /// }
+    /// ```
+    pub async fn with_secret_async<R>(&self, f: impl AsyncFnOnce(&S) -> R) -> R {
+        let guard = self.expose_secret();
+        f(&guard).await
+    }
+
+    /// Expose the secret mutably as pinned, for FFI or crypto state that must stay at
+    /// the same address across calls, such as a self-referential context.
+    ///
+    /// The secret never moves while it lives in the box, this only makes that contract
+    /// explicit in the type. The pages are exposed while the guard is alive, like with
+    /// [`ExposeSecret::expose_secret_mut`].
+    ///
+    /// # Safety
+    ///
+    /// Unless `S` is `Unpin`, once pinned the secret must not be moved out of the box
+    /// until it is dropped, e.g. with [`core::mem::swap`] on the reference returned by
+    /// [`ExposeSecret::expose_secret_mut`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// let mut state = SecretBox::new(Box::new([0u8; 64]));
+    /// let state_ptr = unsafe { state.as_pin() }.as_ptr();
+    ///
+    /// assert_eq!(unsafe { state.as_pin() }.as_ptr(), state_ptr);
+    /// ```
+    pub unsafe fn as_pin(&mut self) -> Pin<SecretGuardMut<'_, S>> {
+        // The secret stays in place until it is dropped, and the caller promises not to
+        // move it out.
+        Pin::new_unchecked(self.expose_secret_mut())
+    }
+
+    /// Expose the secret mutably to `f` and return whatever it returns.
+    pub fn with_secret_mut<R>(&mut self, f: impl FnOnce(&mut S) -> R) -> R {
+        f(&mut self.expose_secret_mut())
+    }
+
+    /// Clone the secret into a [`Zeroizing`] wrapper, for an API that takes an owned
+    /// `Zeroizing<S>`.
+    ///
+    /// The clone leaves locked memory: it lives wherever `S` allocates, unlocked and
+    /// unprotected, so it may be swapped to disk. It is still zeroized once dropped, keep
+    /// it for no longer than the call that needs it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// fn sign(key: zeroize::Zeroizing<Vec<u8>>) -> usize {
+    ///     key.len()
+    /// }
+    ///
+    /// let key = SecretBox::new(Box::new(vec![1u8; 32]));
+    ///
+    /// assert_eq!(sign(key.to_zeroizing()), 32);
+    /// ```
+    pub fn to_zeroizing(&self) -> Zeroizing<S>
+    where
+        S: Clone,
+    {
+        Zeroizing::new(self.with_secret(S::clone))
+    }
+
+    /// Check the secret against a stored `hash` with `f`, e.g. a password hash verifier
+    /// from `argon2` or `bcrypt`, keeping the plaintext within the exposure.
+    ///
+    /// This only scopes the exposure, `f` is responsible for comparing in constant time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretString;
+    ///
+    /// let password = SecretString::from("hunter2");
+    /// let stored = "hunter2".len();
+    ///
+    /// assert!(password.verify_with(&stored, |password, hash| password.len() == *hash));
+    /// ```
+    pub fn verify_with<H: ?Sized>(&self, hash: &H, f: impl FnOnce(&S, &H) -> bool) -> bool {
+        self.with_secret(|secret| f(secret, hash))
+    }
+
+    /// Whether the memory of the secret is locked, so it is never swapped to disk.
+    ///
+    /// This is `false` when the `mlock` feature is disabled, e.g. to log that locking
+    /// could not be guaranteed on this platform. It covers the memory of `S` itself,
+    /// heap buffers it points to are only locked by the constructors that say so.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether the pages of the secret are inaccessible while no guard exposes it, see
+    /// [`Self::new_protected`].
+    ///
+    /// This stays `true` for a protected secret whose pages were made readable for good
+    /// by the `deref` or `secrecy-compat` features.
+    pub fn is_protected(&self) -> bool {
+        self.protection.as_ref().is_some_and(Protection::is_protected)
+    }
+
+    /// Whether the secret sits between two inaccessible guard pages, see
+    /// [`Self::new_guarded`].
+    pub fn is_guarded(&self) -> bool {
+        self.protection.as_ref().is_some_and(|protection| protection.pages().is_guarded())
+    }
+
+    /// Whether the secret is backed by huge pages, see [`Self::new_hugepages`].
+    fn is_huge(&self) -> bool {
+        self.protection.as_ref().is_some_and(|protection| protection.pages().is_huge())
+    }
+
+    /// Size in bytes of the secret value, without exposing it.
+    ///
+    /// This is the size of `S` itself, not of heap buffers it points to, see
+    /// [`SecretVec::len`] and [`SecretString::len`] for those.
+    pub fn len_bytes(&self) -> usize {
+        size_of_val(unsafe { self.inner_secret.as_ref() })
+    }
+
+    /// Length of the secret as returned by `len`, e.g. of a string, without exposing it.
+    ///
+    /// The length of a protected secret is recorded, see [`Protection::len`].
+    pub(crate) fn secret_len(&self, len: impl FnOnce(&S) -> usize) -> usize {
+        self.assert_alive();
+        let measure = || len(unsafe { self.inner_secret.as_ref() });
+
+        match &self.protection {
+            Some(protection) => protection.len(measure),
+            None => measure(),
+        }
+    }
+
+    /// Replace the secret with `new`, e.g. to rotate a key in place.
+    ///
+    /// `new` is locked the same way as the current secret, protected and with its heap
+    /// buffer locked if the current one is, before the current secret is zeroized and
+    /// unlocked. The old value is never moved out of its locked memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of `new` cannot be locked, the current secret is kept then.
+    pub fn replace(&mut self, new: Box<S>) {
+        // Dropping the old secret zeroizes and unlocks it.
+        *self = self.new_like(new);
+    }
+
+    /// Mutate the secret in place through `f`.
+    ///
+    /// If the secret had its heap buffer locked and `f` reallocated it, the new buffer
+    /// is locked instead. The old buffer is freed by its owner, which may not zeroize
+    /// it, so reserve enough capacity upfront rather than growing the secret here.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new heap buffer cannot be locked.
+    pub fn replace_with(&mut self, f: impl FnOnce(&mut S)) {
+        self.with_secret_mut(f);
+
+        if let Some(&LockedHeap { ptr, len, buffer, .. }) = self.locked_heap.as_ref() {
+            if self.with_secret_mut(buffer) != (ptr, len) {
+                // The old buffer was freed by `f`, so it is forgotten rather than unlocked.
+                self.locked_heap = None;
+                unsafe { self.lock_heap(buffer) }.unwrap_or_else(lock::failed);
+            }
+        }
+    }
+
+    /// Lock `secret` the same way as this secret: protected, guarded, locked on fault, in
+    /// huge pages or in the same pool if this one is, and with its heap buffer locked if
+    /// this one has it locked. The debug label is kept as well.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of `secret` cannot be locked.
+    fn new_like(&self, secret: Box<S>) -> Self {
+        self.try_new_like(secret).unwrap_or_else(lock::failed)
+    }
+
+    /// Same as [`Self::new_like`], but returns an error if the memory of `secret` cannot
+    /// be locked.
+    fn try_new_like(&self, secret: Box<S>) -> Result<Self, LockError> {
+        let mut new = match &self.slot {
+            Some(Slot::Pool(slot)) => SecretBox::try_new_in(secret, &slot.pool)?,
+            Some(Slot::Arena(slot)) => SecretBox::try_new_in_arena(secret, &slot.arena)?,
+            None => SecretBoxBuilder::new()
+                .protected(self.is_protected())
+                .guarded(self.is_guarded())
+                .lock_on_fault(self.on_fault)
+                .hugepages(self.is_huge())
+                .build(secret)?,
+        };
+        new.debug_label = self.debug_label;
+
+        if let Some(heap) = &self.locked_heap {
+            // Dropping the new secret on error zeroizes it.
+            unsafe { new.lock_heap(heap.buffer) }?;
+        }
+
+        Ok(new)
+    }
+
+    /// Reinterpret the secret as a `T`, e.g. wrapping a `[u8; 32]` in a key newtype,
+    /// keeping it in the same locked allocation.
+    ///
+    /// The secret is neither copied nor locked again. `T` must have the same size and
+    /// alignment as `S`, and secrets with a locked heap buffer can't be reinterpreted,
+    /// otherwise the secret is given back as the error. `S` is not dropped, only `T`
+    /// is once the new box is.
+    ///
+    /// # Safety
+    ///
+    /// The bytes of the secret must be a valid `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{zeroize::Zeroize, ExposeSecret, SecretBox};
+    ///
+    /// struct Key([u8; 32]);
+    ///
+    /// impl Zeroize for Key {
+    ///     fn zeroize(&mut self) {
+    ///         self.0.zeroize()
+    ///     }
+    /// }
+    ///
+    /// let bytes = SecretBox::new(Box::new([7u8; 32]));
+    /// let key: SecretBox<Key> = unsafe { bytes.transmute_into() }.unwrap();
+    ///
+    /// assert_eq!(key.expose_secret().0, [7; 32]);
+    /// ```
+    // The handle is handed back as is, the secret itself is never moved.
+    #[allow(clippy::result_large_err)]
+    pub unsafe fn transmute_into<T: Zeroize>(self) -> Result<SecretBox<T>, Self> {
+        if size_of::<T>() != size_of::<S>()
+            || align_of::<T>() != align_of::<S>()
+            || self.locked_heap.is_some()
+        {
+            return Err(self);
+        }
+
+        let mut this = ManuallyDrop::new(self);
+        registry::unregister(&mut this);
+
+        let mut secret = SecretBox {
+            inner_secret: this.inner_secret.cast(),
+            protection: ptr::read(&this.protection),
+            slot: ptr::read(&this.slot),
+            locked_heap: None,
+            debug_label: this.debug_label,
+            locked: this.locked,
+            on_fault: this.on_fault,
+            destroyed: this.destroyed,
+            zeroized_by_drop: false,
+            registration: Registration::NONE,
+        };
+        registry::register(&mut secret);
+
+        Ok(secret)
+    }
+
+    /// Print `label` instead of `[REDACTED]` when the secret is formatted with `Debug`,
+    /// e.g. to tell secrets apart in logs.
+    ///
+    /// The label must be a `&'static str` so it is fixed at compile time rather than
+    /// derived from the secret. `Debug` never prints the secret itself, use
+    /// [`Self::len_bytes`] to debug size issues.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// let key = SecretBox::new(Box::new([0u8; 32])).with_debug_label("<signing key>");
+    ///
+    /// assert_eq!(format!("{key:?}"), "SecretBox<[u8; 32]>(<signing key>)");
+    /// ```
+    pub fn with_debug_label(mut self, label: &'static str) -> Self {
+        self.debug_label = label;
+        self
+    }
+
+    /// Rely on the secret zeroizing itself when dropped, instead of zeroizing it before
+    /// dropping it, so a type implementing [`ZeroizeOnDrop`] isn't zeroized twice.
+    ///
+    /// The secret is dropped while its memory is still locked, and only unlocked
+    /// afterwards. Heap buffers locked by this crate, e.g. of a `Vec<u8>`, are still
+    /// zeroized first, since dropping the secret frees them. Copies such as clones
+    /// zeroize as usual.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    /// use zeroize::Zeroizing;
+    ///
+    /// let key = SecretBox::new(Box::new(Zeroizing::new([7u8; 32]))).zeroized_by_drop();
+    /// ```
+    pub fn zeroized_by_drop(mut self) -> Self
+    where
+        S: ZeroizeOnDrop,
+    {
+        self.zeroized_by_drop = true;
+        self
+    }
+
+    /// Derive a new secret from this one, e.g. hashing a password into a key.
+    ///
+    /// `f` runs with the secret exposed and its result is moved, not cloned, into a
+    /// freshly locked box.
+    pub fn map<T: Zeroize>(&self, f: impl FnOnce(&S) -> T) -> SecretBox<T> {
+        SecretBox::new(Box::new(self.with_secret(f)))
+    }
+}
+
+impl<S: Zeroize + Default> SecretBox<S> {
+    /// Create a secret value using a function that can initialize the vale in-place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let key = SecretBox::<[u8; 32]>::new_with_mut(|key| {
+    ///     for (i, byte) in key.iter_mut().enumerate() {
+    ///         *byte = i as u8;
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(key.expose_secret()[31], 31);
+    /// ```
+    pub fn new_with_mut(ctr: impl FnOnce(&mut S)) -> Self {
+        let mut secret = Self::default();
+        ctr(&mut *secret.expose_secret_mut());
+        secret
+    }
+
+    /// Same as [`Self::new_with_mut`], but the initializer can fail, e.g. when reading
+    /// exactly `N` bytes from a source that may run short.
+    ///
+    /// The value is initialized in locked memory and never cloned, unlike with
+    /// [`Self::try_new_with_ctr`]. On error whatever the initializer wrote so far is
+    /// zeroized before the error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    /// use std::io::Read;
+    ///
+    /// let mut source: &[u8] = &[7; 32];
+    /// let key = SecretBox::<[u8; 32]>::try_new_with_mut(|key| source.read_exact(key)).unwrap();
+    /// assert_eq!(key.expose_secret()[31], 7);
+    ///
+    /// let mut short: &[u8] = &[7; 16];
+    /// assert!(SecretBox::<[u8; 32]>::try_new_with_mut(|key| short.read_exact(key)).is_err());
+    /// ```
+    pub fn try_new_with_mut<E>(ctr: impl FnOnce(&mut S) -> Result<(), E>) -> Result<Self, E> {
+        let mut secret = Self::default();
+        // Dropping the secret on error zeroizes it.
+        ctr(&mut *secret.expose_secret_mut())?;
+        Ok(secret)
+    }
+
+    /// Create a secret value using the provided function as a constructor, moving its
+    /// result into memory locked beforehand.
+    ///
+    /// Unlike [`Self::new_with_ctr`] the value is never cloned: it is moved into the
+    /// locked box and the bytes it was moved out of are zeroized. Prefer
+    /// [`Self::new_with_mut`] when the value can be built through a `&mut S`, since then
+    /// it never exists outside the box, and use this method when the constructor has to
+    /// return the value, e.g. a key derived by another library. Heap buffers owned by
+    /// the value are moved along with it, not copied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let key = SecretBox::<[u8; 32]>::new_with_ctr_in_place(|| [7; 32]);
+    ///
+    /// assert_eq!(key.expose_secret()[0], 7);
+    /// ```
+    pub fn new_with_ctr_in_place(ctr: impl FnOnce() -> S) -> Self {
+        let mut secret = Self::default();
+        secret.move_in(&mut MaybeUninit::new(ctr()));
+        secret
+    }
+
+    /// Move `source` into the secret, replacing the current value, and zeroize the bytes
+    /// of `source`, which must be initialized and is not dropped.
+    fn move_in(&mut self, source: &mut MaybeUninit<S>) {
+        self.with_secret_mut(|secret| unsafe {
+            ptr::drop_in_place(secret);
+            ptr::copy_nonoverlapping(source.as_ptr(), secret, 1);
+        });
+
+        source.zeroize();
+    }
+}
+
+impl<const N: usize> SecretBox<[u8; N]> {
+    /// Create a secret array of `N` zeroed bytes, allocated directly on the heap.
+    ///
+    /// Unlike `SecretBox::new(Box::new([0; N]))`, the array is never built on the stack
+    /// first, so large keys don't leave a transient copy behind. Fill it in place through
+    /// [`ExposeSecret::expose_secret_mut`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let mut table = SecretBox::<[u8; 4096]>::new_array_zeroed();
+    /// table.expose_secret_mut().fill(0xff);
+    ///
+    /// assert!(table.expose_secret().iter().all(|&byte| byte == 0xff));
+    /// ```
+    pub fn new_array_zeroed() -> Self {
+        if N == 0 {
+            return Self::new(Box::new([0; N]));
+        }
+
+        // What `Box::new_zeroed` does, which needs a more recent Rust.
+        let layout = Layout::new::<[u8; N]>();
+        let array = unsafe {
+            let ptr = alloc_zeroed(layout);
+            if ptr.is_null() {
+                handle_alloc_error(layout);
+            }
+
+            Box::from_raw(ptr.cast::<[u8; N]>())
+        };
+
+        Self::new(array)
+    }
+
+    /// Call `f` with every byte of the secret in order, while it is exposed.
+    ///
+    /// Unlike iterating over a guard, `f` can't hand the bytes back to be collected,
+    /// keeping the processing within the exposure, e.g. to XOR the secret into a buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// let pad = SecretBox::new(Box::new([0x0f; 4]));
+    /// let mut message = *b"abcd";
+    ///
+    /// let mut i = 0;
+    /// pad.for_each_byte(|byte| {
+    ///     message[i] ^= byte;
+    ///     i += 1;
+    /// });
+    ///
+    /// assert_eq!(message, [0x6e, 0x6d, 0x6c, 0x6b]);
+    /// ```
+    pub fn for_each_byte(&self, f: impl FnMut(u8)) {
+        self.with_secret(|secret| secret.iter().copied().for_each(f))
+    }
+}
+
+impl<const N: usize> TryFrom<&mut [u8]> for SecretBox<[u8; N]> {
+    type Error = TryFromSliceError;
+
+    /// Copy exactly `N` bytes from `src` into a new secret array and zeroize `src`.
+    ///
+    /// If `src` is not `N` bytes long an error is returned and `src` is left untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let mut buf = [1, 2, 3, 4];
+    /// let key = SecretBox::<[u8; 4]>::try_from(&mut buf[..]).unwrap();
+    ///
+    /// assert_eq!(buf, [0; 4]);
+    /// assert_eq!(*key.expose_secret(), [1, 2, 3, 4]);
+    /// ```
+    fn try_from(src: &mut [u8]) -> Result<Self, Self::Error> {
+        let bytes = <&[u8; N]>::try_from(&*src)?;
+
+        let mut secret = Self::new_array_zeroed();
+        secret.with_secret_mut(|secret| secret.copy_from_slice(bytes));
+        src.zeroize();
+
+        Ok(secret)
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for SecretBox<[u8; N]> {
+    /// Copy `bytes` into a new secret array, then zeroize the array passed in.
+    ///
+    /// Only the copy this call takes by value is zeroized. The compiler may have left
+    /// other copies on the stack, e.g. in the caller's binding, so prefer filling
+    /// [`Self::new_array_zeroed`] in place for long-lived keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let key = SecretBox::from([7u8; 32]);
+    ///
+    /// assert_eq!(*key.expose_secret(), [7; 32]);
+    /// ```
+    fn from(mut bytes: [u8; N]) -> Self {
+        let mut secret = Self::new_array_zeroed();
+        secret.with_secret_mut(|secret| secret.copy_from_slice(&bytes));
+        bytes.zeroize();
+
+        secret
+    }
+}
+
+impl<S: Zeroize + AsRef<[u8]>> SecretBox<S> {
+    /// Expose the bytes of the secret to `f` as a pointer and a length, e.g. to pass
+    /// them to a C library.
+    ///
+    /// The pointer is only valid until `f` returns: the secret stays exposed, and
+    /// locked, for the duration of the call and no longer. `f` must not keep the pointer,
+    /// nor write through it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretBox;
+    ///
+    /// extern "C" fn count_ones(ptr: *const u8, len: usize) -> u32 {
+    ///     let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+    ///     bytes.iter().map(|byte| byte.count_ones()).sum()
+    /// }
+    ///
+    /// let key = SecretBox::new(Box::new([0xffu8; 32]));
+    ///
+    /// assert_eq!(key.with_raw_parts(|ptr, len| count_ones(ptr, len)), 256);
+    /// ```
+    pub fn with_raw_parts<R>(&self, f: impl FnOnce(*const u8, usize) -> R) -> R {
+        self.with_secret(|secret| {
+            let bytes = secret.as_ref();
+            f(bytes.as_ptr(), bytes.len())
+        })
+    }
+}
+
+impl<S: Zeroize + AsMut<[u8]>> SecretBox<S> {
+    /// Same as [`Self::with_raw_parts`], exposing the bytes mutably, e.g. to let a C
+    /// library derive a key into them.
+    ///
+    /// The pointer is only valid until `f` returns, and `f` may only write within the
+    /// `len` bytes.
+    pub fn with_raw_parts_mut<R>(&mut self, f: impl FnOnce(*mut u8, usize) -> R) -> R {
+        self.with_secret_mut(|secret| {
+            let bytes = secret.as_mut();
+            f(bytes.as_mut_ptr(), bytes.len())
+        })
+    }
+}
+
+impl<S: Zeroize + Clone> SecretBox<S> {
+    /// Create a secret value using the provided function as a constructor.
+    ///
+    /// The implementation makes an effort to zeroize the locally constructed value
+    /// before it is copied to the heap, and constructing it inside the closure minimizes
+    /// the possibility of it being accidentally copied by other code.
+    ///
+    /// **Note:** using [`Self::new`] or [`Self::new_with_mut`] is preferable when possible,
+    /// since this method's safety relies on empyric evidence and may be violated on some targets.
+    /// For `S: Default`, [`Self::new_with_ctr_in_place`] avoids the clone.
+    pub fn new_with_ctr(ctr: impl FnOnce() -> S) -> Self {
+        let mut data = ctr();
+        let secret = Self::new(Box::new(data.clone()));
+        data.zeroize();
+        secret
+    }
+
+    /// Same as [`Self::new_with_ctr`], but the constructor can be fallible.
+    ///
+    ///
+    /// **Note:** using [`Self::new`] or [`Self::new_with_mut`] is preferable when possible,
+    /// since this method's safety relies on empyric evidence and may be violated on some targets.
+    pub fn try_new_with_ctr<E>(ctr: impl FnOnce() -> Result<S, E>) -> Result<Self, E> {
+        let mut data = ctr()?;
+        let secret = Self::new(Box::new(data.clone()));
+        data.zeroize();
+        Ok(secret)
+    }
+}
+
+impl<S: Zeroize + Default> Default for SecretBox<S> {
+    fn default() -> Self {
+        let inner_secret = Box::<S>::default();
+        SecretBox::new(inner_secret)
+    }
+}
+
+impl<S: Zeroize> Debug for SecretBox<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBox<{}>({})", any::type_name::<S>(), self.debug_label)
+    }
+}
+
+impl<S: CloneableSecret> SecretBox<S> {
+    /// Clone the secret into a newly locked allocation, returning an error if its
+    /// memory cannot be locked.
+    ///
+    /// On failure the clone is zeroized before the error is returned.
+    pub fn try_clone(&self) -> Result<Self, LockError> {
+        self.try_new_like(Box::new((*self.expose_secret()).clone()))
+    }
+}
+
+impl<S> Clone for SecretBox<S>
+where
+    S: CloneableSecret,
+{
+    /// Clone the secret into a newly locked allocation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the clone cannot be locked, see
+    /// [`SecretBox::try_clone`] for a fallible version.
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_or_else(lock::failed)
+    }
+}
+
+impl<S: Zeroize> ExposeSecret<S> for SecretBox<S> {
+    /// # Panics
+    ///
+    /// Panics if the secret was destroyed, see [`SecretBox::destroy`].
+    fn expose_secret(&self) -> SecretGuard<'_, S> {
+        self.assert_alive();
+        trace::exposed(self);
+        let data = unsafe { self.inner_secret.as_ref() };
+        SecretGuard::with_protection(data, self.protection.as_ref())
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the secret was destroyed, see [`SecretBox::destroy`].
+    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, S> {
+        self.assert_alive();
+        trace::exposed(self);
+        let data = unsafe { &mut *self.inner_secret.as_ptr() };
+        SecretGuardMut::with_protection(data, self.protection.as_ref())
+    }
+}
+
+/// Read the secret without a guard, see the `deref` feature.
+///
+/// A protected secret is made readable for good the first time it is dereferenced,
+/// since the reference is not tied to a guard.
+#[cfg(feature = "deref")]
+impl<S: Zeroize> Deref for SecretBox<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        self.assert_alive();
+
+        if let Some(protection) = &self.protection {
+            protection.disable();
+        }
+
+        unsafe { self.inner_secret.as_ref() }
+    }
+}
+
+/// Secret Guard that holds a reference to the secret.
+///
+/// The guard is `Send` and `Sync` whenever `S` is `Sync`, like a `&S`, so it can be
+/// moved into a scoped thread that reads the secret while the owner waits. A protected
+/// secret stays readable until the guard is dropped, on whichever thread that is.
+///
+/// # Examples
+///
+/// ```
+/// use shush::SecretArc;
+/// use std::thread;
+///
+/// let key = SecretArc::new_protected(Box::new([7u8; 32]));
+/// let guard = key.expose_secret();
+///
+/// let sum = thread::scope(|scope| {
+///     scope.spawn(move || guard.iter().map(|&byte| u32::from(byte)).sum::<u32>()).join()
+/// });
+///
+/// assert_eq!(sum.unwrap(), 7 * 32);
+/// ```
+pub struct SecretGuard<'a, S>
+where
+    S: Zeroize + ?Sized,
+{
+    data: &'a S,
+    protection: Option<&'a Protection>,
+}
+
+impl<S> Deref for SecretGuard<'_, S>
+where
+    S: Zeroize + ?Sized,
+{
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+/// Secret Guard that holds a mutable to reference to the secret.
+///
+/// Like a `&mut S`, the guard is `Send` whenever `S` is `Send`, and `Sync` whenever `S`
+/// is `Sync`.
+pub struct SecretGuardMut<'a, S>
+where
+    S: Zeroize + ?Sized,
+{
+    data: &'a mut S,
+    protection: Option<&'a Protection>,
+}
+
+impl<S> Deref for SecretGuardMut<'_, S>
+where
+    S: Zeroize + ?Sized,
+{
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl<S> DerefMut for SecretGuardMut<'_, S>
+where
+    S: Zeroize + ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.data
+    }
+}
+
+// Indexing borrows from the guard like the byte views below, e.g. `guard[0]` or
+// `guard[..16]` on a `Vec<u8>`.
+impl<S, I> Index<I> for SecretGuard<'_, S>
+where
+    S: Zeroize + Index<I> + ?Sized,
+{
+    type Output = S::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+impl<S, I> Index<I> for SecretGuardMut<'_, S>
+where
+    S: Zeroize + Index<I> + ?Sized,
+{
+    type Output = S::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+impl<S, I> IndexMut<I> for SecretGuardMut<'_, S>
+where
+    S: Zeroize + IndexMut<I> + ?Sized,
+{
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        &mut self.data[index]
+    }
+}
+
+// Byte views of a guarded secret, for APIs taking `&[u8]`. They borrow from the guard,
+// so the bytes are still only reachable while the secret is exposed.
+impl AsRef<[u8]> for SecretGuard<'_, Vec<u8>> {
+    fn as_ref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for SecretGuard<'_, [u8; N]> {
+    fn as_ref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl AsRef<[u8]> for SecretGuardMut<'_, Vec<u8>> {
+    fn as_ref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl AsMut<[u8]> for SecretGuardMut<'_, Vec<u8>> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for SecretGuardMut<'_, [u8; N]> {
+    fn as_ref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl<const N: usize> AsMut<[u8]> for SecretGuardMut<'_, [u8; N]> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+}
+
+impl<const N: usize> SecretGuard<'_, [u8; N]> {
+    /// The secret as an array reference, for APIs taking `&[u8; N]` such as the key
+    /// constructors of many RustCrypto crates.
+    ///
+    /// Like the byte views, it borrows from the guard.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// fn expand(key: &[u8; 32]) -> u8 {
+    ///     key[0]
+    /// }
+    ///
+    /// let key = SecretBox::new(Box::new([7u8; 32]));
+    ///
+    /// assert_eq!(expand(key.expose_secret().as_array()), 7);
+    /// ```
+    pub fn as_array(&self) -> &[u8; N] {
+        self.data
+    }
+}
+
+impl SecretGuard<'_, Vec<u8>> {
+    /// The secret as an array reference of `N` bytes, or `None` if it isn't exactly `N`
+    /// bytes long, e.g. for a key whose length is only known once loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// let key = SecretBox::new(Box::new(vec![7u8; 32]));
+    /// let guard = key.expose_secret();
+    ///
+    /// assert_eq!(guard.try_as_array::<32>(), Some(&[7; 32]));
+    /// assert_eq!(guard.try_as_array::<16>(), None);
+    /// ```
+    pub fn try_as_array<const N: usize>(&self) -> Option<&[u8; N]> {
+        self.data.as_slice().try_into().ok()
+    }
+}
+
+impl<S> Drop for SecretGuard<'_, S>
+where
+    S: Zeroize + ?Sized,
+{
+    fn drop(&mut self) {
+        if let Some(protection) = self.protection {
+            protection.conceal();
+        }
+    }
+}
+
+impl<S> Drop for SecretGuardMut<'_, S>
+where
+    S: Zeroize + ?Sized,
+{
+    fn drop(&mut self) {
+        if let Some(protection) = self.protection {
+            protection.conceal();
+        }
+    }
+}
+
+impl<'a, S: Zeroize + ?Sized> SecretGuard<'a, S> {
+    /// Create a new SecretGuard instance.
+    pub fn new(data: &'a S) -> Self {
+        Self::with_protection(data, None)
+    }
+
+    /// Create a guard that keeps the protected pages of `data` readable while it is alive.
+    pub(crate) fn with_protection(data: &'a S, protection: Option<&'a Protection>) -> Self {
+        if let Some(protection) = protection {
+            protection.expose(Prot::ReadOnly);
+        }
+
+        Self { data, protection }
+    }
+
+    /// Make a guard for a part of the secret, keeping the same exposure.
+    pub(crate) fn map<T: Zeroize + ?Sized>(
+        orig: Self,
+        f: impl FnOnce(&'a S) -> &'a T,
+    ) -> SecretGuard<'a, T> {
+        let orig = ManuallyDrop::new(orig);
+
+        SecretGuard {
+            data: f(orig.data),
+            protection: orig.protection,
+        }
+    }
+
+    /// Borrow the exposed secret again, e.g. to hand a guard to a helper while keeping
+    /// this one.
+    ///
+    /// Guards are deliberately not `Clone`. A reborrow can't outlive this guard, so it
+    /// reuses its exposure without changing the access of the pages again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox, SecretGuard};
+    ///
+    /// fn first_byte(guard: SecretGuard<'_, [u8; 4]>) -> u8 {
+    ///     guard[0]
+    /// }
+    ///
+    /// let key = SecretBox::new_protected(Box::new([1u8, 2, 3, 4]));
+    /// let guard = key.expose_secret();
+    ///
+    /// assert_eq!(first_byte(guard.reborrow()), 1);
+    /// assert_eq!(guard[3], 4);
+    /// ```
+    pub fn reborrow(&self) -> SecretGuard<'_, S> {
+        SecretGuard {
+            data: self.data,
+            protection: None,
+        }
+    }
+}
+
+impl<'a, S: Zeroize + ?Sized> SecretGuardMut<'a, S> {
+    /// Create a new SecretGuard instance.
+    pub fn new(data: &'a mut S) -> Self {
+        Self::with_protection(data, None)
+    }
+
+    /// Create a guard that keeps the protected pages of `data` writable while it is alive.
+    pub(crate) fn with_protection(data: &'a mut S, protection: Option<&'a Protection>) -> Self {
+        if let Some(protection) = protection {
+            protection.expose(Prot::ReadWrite);
+        }
+
+        Self { data, protection }
+    }
+
+    /// Same as [`SecretGuard::map`], for a mutable part of the secret.
+    pub(crate) fn map<T: Zeroize + ?Sized>(
+        orig: Self,
+        f: impl FnOnce(&'a mut S) -> &'a mut T,
+    ) -> SecretGuardMut<'a, T> {
+        let orig = ManuallyDrop::new(orig);
+        // The original guard is never used or dropped again, so its reference moves out.
+        let data = unsafe { ptr::read(&orig.data) };
+
+        SecretGuardMut {
+            data: f(data),
+            protection: orig.protection,
+        }
+    }
+}
+
+/// Marker trait for secrets which are allowed to be cloned
+///
+/// It is implemented for primitive numbers, `String`, and arrays and `Vec`s of
+/// cloneable secrets. Opt your own types in with an empty impl:
+///
+/// ```
+/// use shush::{zeroize::Zeroize, CloneableSecret};
+///
+/// #[derive(Clone)]
+/// struct ApiKey([u8; 32]);
+///
+/// impl Zeroize for ApiKey {
+///     fn zeroize(&mut self) {
+///         self.0.zeroize()
+///     }
+/// }
+///
+/// impl CloneableSecret for ApiKey {}
+/// ```
+///
+/// Every clone of a [`SecretBox`] is a new locked allocation, so cloning doubles the
+/// locked memory used by the secret. Locked memory is limited per process, by
+/// `RLIMIT_MEMLOCK` on Unix, and cloning panics once the clone can't be locked anymore.
+pub trait CloneableSecret: Clone + Zeroize {}
+
+macro_rules! impl_cloneable_secret {
+    ($($ty:ty),*) => {
+        $(impl CloneableSecret for $ty {})*
+    };
+}
+
+impl_cloneable_secret!(
+    bool, char, f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, String
+);
+
+impl<S: CloneableSecret, const N: usize> CloneableSecret for [S; N] {}
+
+impl<S: CloneableSecret> CloneableSecret for Vec<S> {}
+
+impl CloneableSecret for Box<[u8]> {}
+
+/// Create a SecretGuard that holds a reference to the secret
+pub trait ExposeSecret<S: Zeroize> {
+    /// Expose secret as non-mutable.
+    fn expose_secret(&self) -> SecretGuard<'_, S>;
+
+    /// Expose secret as mutable.
+    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, S>;
+}
+
+/// Expose two secrets to `f` at once and return whatever it returns, e.g. to derive a
+/// key from a salt and input key material.
+///
+/// Both secrets are only reachable for the duration of the closure, like with
+/// [`SecretBox::with_secret`]. `a` and `b` may be the same secret.
+///
+/// # Examples
+///
+/// ```
+/// use shush::{with_two, SecretBox};
+///
+/// let salt = SecretBox::new(Box::new([0x0fu8; 4]));
+/// let ikm = SecretBox::new_protected(Box::new([0xf0u8; 4]));
+///
+/// let mixed = with_two(&salt, &ikm, |salt, ikm| salt[0] | ikm[0]);
+/// assert_eq!(mixed, 0xff);
+/// ```
+pub fn with_two<A: Zeroize, B: Zeroize, R>(
+    a: &SecretBox<A>,
+    b: &SecretBox<B>,
+    f: impl FnOnce(&A, &B) -> R,
+) -> R {
+    f(&a.expose_secret(), &b.expose_secret())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{cell::Cell, slice};
+    use std::panic::AssertUnwindSafe;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use heap::{assert_heap_locked, vec_buffer};
+
+    #[derive(Debug, Clone, Default)]
+    struct TestSecret {
+        data: Vec<u8>,
+    }
+
+    impl TestSecret {
+        fn new(size: usize) -> Self {
+            let mut data = vec![0; size];
+            data[0] = 1;
+            Self { data }
+        }
+
+        fn check_non_zero(&self) -> bool {
+            self.data.iter().any(|&x| x != 0)
+        }
+
+        fn check_zero(&self) -> bool {
+            self.data.iter().all(|&x| x == 0)
+        }
+    }
+
+    impl Zeroize for TestSecret {
+        fn zeroize(&mut self) {
+            self.data = Vec::new();
+        }
+    }
+
+    #[test]
+    fn test_secret_box_drop_zeroizes() {
+        let secret = Box::new(TestSecret::new(10));
+        let secret_box = SecretBox::new(secret);
+        assert!((*secret_box.expose_secret()).check_non_zero());
+
+        drop(secret_box);
+
+        // Verify that secret is zeroized after drop
+        // This requires checking the memory, which is not straightforward in Rust.
+        // Here we rely on the zeroize trait to ensure it zeroizes.
+        assert!(TestSecret::default().check_zero());
+    }
+
+    #[derive(Zeroize)]
+    struct Credentials {
+        key: [u8; 32],
+        certificate: Vec<u8>,
+        #[zeroize(skip)]
+        key_id: u32,
+    }
+
+    #[test]
+    fn test_derived_zeroize_clears_every_field() {
+        let mut credentials = SecretBox::new(Box::new(Credentials {
+            key: [7; 32],
+            certificate: vec![9; 100],
+            key_id: 42,
+        }));
+
+        // Destroying wipes the secret the same way dropping it does, but keeps the
+        // memory around to check.
+        credentials.destroy();
+
+        let credentials = unsafe { credentials.inner_secret.as_ref() };
+        let certificate = &credentials.certificate;
+        let certificate =
+            unsafe { slice::from_raw_parts(certificate.as_ptr(), certificate.capacity()) };
+
+        assert_eq!(credentials.key, [0; 32]);
+        assert!(credentials.certificate.is_empty());
+        assert!(certificate.len() >= 100);
+        assert!(certificate.iter().all(|&byte| byte == 0));
+        assert_eq!(credentials.key_id, 42);
+    }
+
+    #[test]
+    fn test_secret_box_expose_secret_mut() {
+        let secret = Box::new(TestSecret::new(10));
+        let mut secret_box = SecretBox::new(secret);
+        {
+            let mut exposed = secret_box.expose_secret_mut();
+            (*exposed).data[0] = 42;
+        }
+
+        assert_eq!((*secret_box.expose_secret()).data[0], 42);
+    }
+
+    #[test]
+    fn test_secret_box_new_with_ctr() {
+        let secret_box = SecretBox::new_with_ctr(|| TestSecret::new(10));
+        assert!((*secret_box.expose_secret()).check_non_zero());
+    }
+
+    #[test]
+    fn test_secret_box_try_new_with_ctr() {
+        let result: Result<SecretBox<TestSecret>, &'static str> =
+            SecretBox::try_new_with_ctr(|| Ok(TestSecret::new(10)));
+
+        match result {
+            Ok(secret_box) => assert!((*secret_box.expose_secret()).check_non_zero()),
+            Err(_) => panic!("Expected Ok variant"),
+        }
+    }
+
+    #[test]
+    fn test_secret_box_try_new() {
+        let result = SecretBox::try_new(Box::new(TestSecret::new(10)));
+
+        match result {
+            Ok(secret_box) => assert!((*secret_box.expose_secret()).check_non_zero()),
+            Err(err) => panic!("Expected Ok variant, got {err}"),
+        }
+    }
+
+    #[test]
+    fn test_secret_box_expose_secret_shared() {
+        let secret_box = SecretBox::new(Box::new(TestSecret::new(10)));
+        let (first, second) = (&secret_box, &secret_box);
+
+        let first_guard = first.expose_secret();
+        let second_guard = second.expose_secret();
+
+        assert_eq!((*first_guard).data, (*second_guard).data);
+    }
+
+    #[test]
+    fn test_secret_box_with_secret() {
+        let secret_box = SecretBox::new(Box::new(TestSecret::new(10)));
+
+        let len = secret_box.with_secret(|secret| secret.data.len());
+
+        assert_eq!(len, 10);
+    }
+
+    #[test]
+    fn test_secret_box_with_secret_mut() {
+        let mut secret_box = SecretBox::new(Box::new(TestSecret::new(10)));
+
+        let previous = secret_box.with_secret_mut(|secret| {
+            let previous = secret.data[0];
+            secret.data[0] = 42;
+            previous
+        });
+
+        assert_eq!(previous, 1);
+        assert_eq!(secret_box.with_secret(|secret| secret.data[0]), 42);
+    }
+
+    #[test]
+    fn test_secret_box_len_bytes() {
+        let secret_box = SecretBox::new_protected(Box::new([0u8; 48]));
+
+        assert_eq!(secret_box.len_bytes(), 48);
+    }
+
+    #[test]
+    fn test_secret_box_map() {
+        let secret_box = SecretBox::new(Box::new([3u8; 32]));
+
+        let derived = secret_box.map(|key| {
+            let mut expanded = [0u8; 64];
+            expanded[..32].copy_from_slice(key);
+            expanded[32..].copy_from_slice(key);
+            expanded
+        });
+
+        assert_eq!(*derived.expose_secret(), [3; 64]);
+    }
+
+    #[test]
+    fn test_secret_box_new_array_zeroed() {
+        // `new` panics if the array could not be locked.
+        let mut secret_box = SecretBox::<[u8; 4096]>::new_array_zeroed();
+        assert_eq!(secret_box.locked_region().1, 4096);
+        assert!(secret_box.expose_secret().iter().all(|&byte| byte == 0));
+
+        secret_box.expose_secret_mut().fill(1);
+        secret_box.zeroize();
+
+        assert!(secret_box.expose_secret().iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_for_each_byte_xor_into_buffer() {
+        let key = SecretBox::new_protected(Box::new([0x5a; 8]));
+        let mut out = *b"plaintxt";
+
+        let mut i = 0;
+        key.for_each_byte(|byte| {
+            out[i] ^= byte;
+            i += 1;
+        });
+
+        assert_eq!(i, 8);
+        assert_eq!(out.map(|byte| byte ^ 0x5a), *b"plaintxt");
+    }
+
+    #[test]
+    fn test_try_from_slice_zeroizes_source() {
+        let mut src = [7u8; 32];
+
+        let secret = SecretBox::<[u8; 32]>::try_from(&mut src[..]).unwrap();
+
+        assert_eq!(src, [0; 32]);
+        assert_eq!(*secret.expose_secret(), [7; 32]);
+    }
+
+    #[test]
+    fn test_try_from_slice_wrong_length() {
+        let mut src = [7u8; 31];
+
+        assert!(SecretBox::<[u8; 32]>::try_from(&mut src[..]).is_err());
+        assert!(SecretBox::<[u8; 30]>::try_from(&mut src[..]).is_err());
+        assert_eq!(src, [7; 31]);
+    }
+
+    #[test]
+    fn test_from_array_literal() {
+        let secret = SecretBox::from([1, 2, 3, 4]);
+        let empty: SecretBox<[u8; 0]> = [].into();
+
+        assert_eq!(*secret.expose_secret(), [1, 2, 3, 4]);
+        assert!(secret.is_locked() == lock::ENABLED);
+        assert!(empty.expose_secret().is_empty());
+    }
+
+    #[test]
+    fn test_guard_as_array() {
+        let key = SecretBox::new_protected(Box::new([1u8, 2, 3, 4]));
+        let bytes = SecretBox::new(Box::new(vec![5u8, 6, 7]));
+
+        let guard = key.expose_secret();
+        let array: &[u8; 4] = guard.as_array();
+        assert_eq!(*array, [1, 2, 3, 4]);
+
+        let guard = bytes.expose_secret();
+        assert_eq!(guard.try_as_array::<3>(), Some(&[5, 6, 7]));
+        assert_eq!(guard.try_as_array::<4>(), None);
+        assert_eq!(guard.try_as_array::<0>(), None);
+    }
+
+    #[test]
+    fn test_secret_box_protected_expose() {
+        let mut secret_box = SecretBox::new_protected(Box::new([1u8; 32]));
+
+        assert_eq!(*secret_box.expose_secret(), [1; 32]);
+
+        secret_box.expose_secret_mut()[0] = 42;
+
+        assert_eq!(secret_box.expose_secret()[0], 42);
+    }
+
+    #[test]
+    fn test_secret_box_protected_nested_guards() {
+        let secret_box = SecretBox::new_protected(Box::new([1u8; 32]));
+
+        let outer = secret_box.expose_secret();
+        {
+            let inner = secret_box.expose_secret();
+            assert_eq!(inner[0], 1);
+        }
+
+        // Dropping the inner guard must not make the pages inaccessible yet.
+        assert_eq!(outer[31], 1);
+    }
+
+    #[test]
+    fn test_with_two_combines_secrets() {
+        let salt = SecretBox::new_protected(Box::new(*b"salt"));
+        let ikm = SecretBox::new_guarded(Box::new(b"input key".to_vec()));
+
+        let combined = with_two(&salt, &ikm, |salt, ikm| {
+            let mut out = [0u8; 4];
+            for (i, byte) in ikm.iter().enumerate() {
+                out[i % 4] ^= byte ^ salt[i % 4];
+            }
+            out
+        });
+
+        let mut expected = [0u8; 4];
+        for (i, byte) in b"input key".iter().enumerate() {
+            expected[i % 4] ^= byte ^ b"salt"[i % 4];
+        }
+        assert_eq!(combined, expected);
+        assert!(with_two(&salt, &salt, |a, b| a == b));
+    }
+
+    #[test]
+    fn test_guard_reborrows() {
+        let secret_box = SecretBox::new_protected(Box::new([1u8; 32]));
+        let guard = secret_box.expose_secret();
+
+        let first = guard.reborrow();
+        let second = guard.reborrow();
+
+        assert!(first.protection.is_none() && second.protection.is_none());
+        assert_eq!(first[0] + second[31], 2);
+
+        drop(first);
+        drop(second);
+        assert_eq!(guard[0], 1);
+    }
+
+    #[test]
+    fn test_secret_box_protected_clone() {
+        #[derive(Clone, Default)]
+        struct Key([u8; 32]);
+
+        impl Zeroize for Key {
+            fn zeroize(&mut self) {
+                self.0.zeroize()
+            }
+        }
+
+        impl CloneableSecret for Key {}
+
+        let secret_box = SecretBox::new_protected(Box::new(Key([7; 32])));
+        let clone = secret_box.clone();
+        drop(secret_box);
+
+        assert!(clone.protection.as_ref().unwrap().is_protected());
+        assert_eq!(clone.expose_secret().0, [7; 32]);
+    }
+
+    extern "C" fn xor_fold(ptr: *const u8, len: usize) -> u8 {
+        unsafe { slice::from_raw_parts(ptr, len) }.iter().fold(0, |acc, byte| acc ^ byte)
+    }
+
+    extern "C" fn fill_incrementing(ptr: *mut u8, len: usize) {
+        for i in 0..len {
+            unsafe { *ptr.add(i) = i as u8 };
+        }
     }
 
     #[test]
-    fn test_secret_box_try_new_with_ctr() {
-        let result: Result<SecretBox<TestSecret>, &'static str> =
-            SecretBox::try_new_with_ctr(|| Ok(TestSecret::new(10)));
+    fn test_with_raw_parts_ffi() {
+        let key = SecretBox::new_protected(Box::new([0u8, 1, 2, 4, 8]));
+        let password = SecretString::from("ab");
 
-        match result {
-            Ok(mut secret_box) => assert!((*secret_box.expose_secret()).check_non_zero()),
-            Err(_) => panic!("Expected Ok variant"),
+        assert_eq!(key.with_raw_parts(|ptr, len| xor_fold(ptr, len)), 15);
+        assert_eq!(password.with_raw_parts(|ptr, len| xor_fold(ptr, len)), b'a' ^ b'b');
+    }
+
+    #[test]
+    fn test_with_raw_parts_mut_ffi() {
+        let mut key = SecretBox::new_protected(Box::new([0xffu8; 16]));
+        let mut vec = SecretBox::from_slice_zeroizing(&mut [0xff; 4]);
+
+        key.with_raw_parts_mut(|ptr, len| fill_incrementing(ptr, len));
+        vec.with_raw_parts_mut(|ptr, len| fill_incrementing(ptr, len));
+
+        assert_eq!(key.expose_secret()[15], 15);
+        assert_eq!(*vec.expose_secret(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_verify_with() {
+        fn checksum(bytes: &[u8]) -> u32 {
+            bytes.iter().fold(17, |sum, &byte| sum.wrapping_mul(31) ^ u32::from(byte))
+        }
+
+        let password = SecretBox::new_protected(Box::new(*b"correct horse"));
+        let verify = |secret: &[u8; 13], hash: &u32| checksum(secret) == *hash;
+
+        assert!(password.verify_with(&checksum(b"correct horse"), verify));
+        assert!(!password.verify_with(&checksum(b"wrong battery"), verify));
+    }
+
+    #[test]
+    fn test_small_secrets_get_pages_of_their_own() {
+        let page_size = page::page_size();
+        let a = SecretBox::new(Box::new(1u8));
+        let b = SecretBox::new(Box::new(2u8));
+
+        for (ptr, len) in [a.locked_region(), b.locked_region()] {
+            assert_eq!(ptr as usize % page_size, 0);
+            assert_eq!(len, page_size);
+        }
+        assert_ne!(a.locked_region().0, b.locked_region().0);
+        assert_eq!(a.with_secret(|secret| secret as *const u8), a.locked_region().0);
+    }
+
+    #[test]
+    fn test_secret_box_clone_is_independent() {
+        let mut secret_box = SecretBox::new(Box::new([5u8; 32]));
+
+        let clone = secret_box.clone();
+        assert_ne!(secret_box.locked_region().0, clone.locked_region().0);
+
+        secret_box.zeroize();
+        assert_eq!(*secret_box.expose_secret(), [0; 32]);
+        assert_eq!(*clone.expose_secret(), [5; 32]);
+    }
+
+    #[test]
+    fn test_secret_box_drop_during_unwind() {
+        let result = std::panic::catch_unwind(|| {
+            let _secret_box = SecretBox::new(Box::new(TestSecret::new(10)));
+            panic!("unwinding with a live secret");
+        });
+
+        assert!(result.is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_secret_box_virtual_lock() {
+        let secret_box = SecretBox::try_new(Box::new([1u8; 64]))
+            .unwrap_or_else(|err| panic!("VirtualLock failed: {err}"));
+
+        assert_eq!((*secret_box.expose_secret())[0], 1);
+
+        drop(secret_box);
+    }
+
+    #[test]
+    fn test_as_pin_keeps_address() {
+        struct Pinned {
+            state: [u8; 16],
+            _pin: core::marker::PhantomPinned,
+        }
+
+        impl Zeroize for Pinned {
+            fn zeroize(&mut self) {
+                self.state.zeroize();
+            }
+        }
+
+        let mut secret = SecretBox::new_protected(Box::new(Pinned {
+            state: [1; 16],
+            _pin: core::marker::PhantomPinned,
+        }));
+
+        let first = unsafe { secret.as_pin() };
+        let address: *const Pinned = &*first;
+        drop(first);
+
+        let mut second = unsafe { secret.as_pin() };
+        assert_eq!(&*second as *const Pinned, address);
+        unsafe { second.as_mut().get_unchecked_mut() }.state[0] = 2;
+        drop(second);
+
+        assert_eq!(secret.expose_secret().state[..2], [2, 1]);
+    }
+
+    #[test]
+    fn test_new_bounded_under_limit() {
+        let secret = SecretBox::new_bounded::<32>(Box::new([7u8; 32])).unwrap();
+        let unbounded_heap = SecretBox::new_bounded::<24>(Box::new(vec![1u8; 4096])).unwrap();
+
+        assert_eq!(*secret.expose_secret(), [7; 32]);
+        assert_eq!(unbounded_heap.expose_secret().len(), 4096);
+    }
+
+    #[test]
+    fn test_new_bounded_over_limit() {
+        let err = SecretBox::new_bounded::<32>(Box::new([7u8; 33])).unwrap_err();
+
+        assert_eq!(err, SizeError { size: 33, max: 32 });
+        assert_eq!(format!("{err}"), "secret of 33 bytes exceeds the maximum of 32 bytes");
+        assert!(SecretBox::new_bounded::<0>(Box::new(0u8)).is_err());
+    }
+
+    #[test]
+    fn test_lock_error_does_not_leak_secret() {
+        let err = LockError::last_os_error(10);
+
+        assert_eq!(err.size(), 10);
+        assert!(format!("{err}").contains("10 bytes"));
+        #[cfg(feature = "std")]
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    fn checksum(bytes: &[u8]) -> u32 {
+        bytes.iter().map(|&byte| u32::from(byte)).sum()
+    }
+
+    #[test]
+    fn test_guard_as_ref_bytes() {
+        let vec = SecretBox::new(Box::new(vec![1u8, 2, 3]));
+        let array = SecretBox::new_protected(Box::new([4u8; 4]));
+
+        assert_eq!(checksum(vec.expose_secret().as_ref()), 6);
+        assert_eq!(checksum(array.expose_secret().as_ref()), 16);
+    }
+
+    #[test]
+    fn test_guard_mut_as_mut_bytes() {
+        fn fill(bytes: &mut [u8]) {
+            bytes.fill(0xaa);
+        }
+
+        let mut vec = SecretBox::new(Box::new(vec![0u8; 3]));
+        let mut array = SecretBox::new(Box::new([0u8; 4]));
+
+        fill(vec.expose_secret_mut().as_mut());
+        fill(array.expose_secret_mut().as_mut());
+
+        assert_eq!(checksum(vec.expose_secret_mut().as_ref()), 3 * 0xaa);
+        assert_eq!(checksum(array.expose_secret_mut().as_ref()), 4 * 0xaa);
+    }
+
+    #[test]
+    fn test_new_with_ctr_in_place() {
+        let secret = SecretBox::new_with_ctr_in_place(|| TestSecret::new(10));
+
+        assert_eq!(secret.with_secret(|secret| secret.data.len()), 10);
+        assert!(secret.expose_secret().check_non_zero());
+    }
+
+    #[test]
+    fn test_move_in_leaves_no_residue() {
+        let mut secret = SecretBox::<[u8; 32]>::default();
+        let mut source = MaybeUninit::new([0xaa; 32]);
+
+        secret.move_in(&mut source);
+
+        assert_eq!(*secret.expose_secret(), [0xaa; 32]);
+        let residue = unsafe { slice::from_raw_parts(source.as_ptr().cast::<u8>(), 32) };
+        assert!(residue.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_replace_zeroizes_old_secret() {
+        static ZEROIZED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Key(u8);
+
+        impl Zeroize for Key {
+            fn zeroize(&mut self) {
+                ZEROIZED.fetch_add(self.0 as usize, Ordering::SeqCst);
+                self.0.zeroize();
+            }
+        }
+
+        let mut secret = SecretBox::new_protected(Box::new(Key(1)));
+        secret.replace(Box::new(Key(2)));
+
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 1);
+        assert_eq!(secret.expose_secret().0, 2);
+
+        drop(secret);
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_to_zeroizing_clone_is_zeroized_on_drop() {
+        static ZEROIZED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Clone)]
+        struct Key(u8);
+
+        impl Zeroize for Key {
+            fn zeroize(&mut self) {
+                ZEROIZED.fetch_add(self.0 as usize, Ordering::SeqCst);
+                self.0.zeroize();
+            }
+        }
+
+        let secret = SecretBox::new_protected(Box::new(Key(5)));
+
+        let clone = secret.to_zeroizing();
+        assert_eq!(clone.0, 5);
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 0);
+
+        drop(clone);
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 5);
+        assert_eq!(secret.expose_secret().0, 5);
+    }
+
+    #[test]
+    fn test_to_zeroizing_vec() {
+        let secret = SecretBox::from_slice_zeroizing(&mut [1, 2, 3]);
+
+        let clone = secret.to_zeroizing();
+
+        assert_eq!(*clone, [1, 2, 3]);
+        assert_ne!(clone.as_ptr(), secret.expose_secret().as_ptr());
+    }
+
+    #[test]
+    fn test_try_new_with_mut() {
+        use std::io::Read;
+
+        let mut source: &[u8] = b"0123456789abcdef";
+        let secret = SecretBox::<[u8; 8]>::try_new_with_mut(|key| source.read_exact(key)).unwrap();
+
+        assert_eq!(*secret.expose_secret(), *b"01234567");
+        assert!(secret.is_locked() || !lock::ENABLED);
+        assert_eq!(source, b"89abcdef");
+    }
+
+    #[test]
+    fn test_try_new_with_mut_zeroizes_on_error() {
+        static WIPED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Default)]
+        struct Key([u8; 4]);
+
+        impl Zeroize for Key {
+            fn zeroize(&mut self) {
+                let written = self.0.iter().filter(|&&byte| byte != 0).count();
+                WIPED.fetch_add(written, Ordering::SeqCst);
+                self.0.zeroize();
+            }
+        }
+
+        let result = SecretBox::<Key>::try_new_with_mut(|key| {
+            key.0[..3].copy_from_slice(&[1, 2, 3]);
+            Err("source ran short")
+        });
+
+        assert_eq!(result.err(), Some("source ran short"));
+        assert_eq!(WIPED.load(Ordering::SeqCst), 3);
+    }
+
+    static DROP_ZEROIZED: AtomicUsize = AtomicUsize::new(0);
+
+    struct SelfZeroizing([u8; 16]);
+
+    impl Zeroize for SelfZeroizing {
+        fn zeroize(&mut self) {
+            DROP_ZEROIZED.fetch_add(1, Ordering::SeqCst);
+            self.0.zeroize();
+        }
+    }
+
+    impl Drop for SelfZeroizing {
+        fn drop(&mut self) {
+            self.zeroize();
+        }
+    }
+
+    impl ZeroizeOnDrop for SelfZeroizing {}
+
+    #[test]
+    fn test_zeroized_by_drop_zeroizes_once() {
+        let zeroized = || DROP_ZEROIZED.load(Ordering::SeqCst);
+
+        drop(SecretBox::new(Box::new(SelfZeroizing([1; 16]))));
+        assert_eq!(zeroized(), 2);
+
+        drop(SecretBox::new(Box::new(SelfZeroizing([1; 16]))).zeroized_by_drop());
+        assert_eq!(zeroized(), 3);
+
+        drop(SecretBox::new_protected(Box::new(SelfZeroizing([1; 16]))).zeroized_by_drop());
+        assert_eq!(zeroized(), 4);
+
+        let pool = SecretPool::new(16, 1);
+        drop(SecretBox::new_in(Box::new(SelfZeroizing([1; 16])), &pool).zeroized_by_drop());
+        assert_eq!(zeroized(), 5);
+        assert_eq!(pool.available(), 1);
+
+        let mut destroyed = SecretBox::new(Box::new(SelfZeroizing([1; 16]))).zeroized_by_drop();
+        destroyed.destroy();
+        drop(destroyed);
+        assert_eq!(zeroized(), 7);
+    }
+
+    #[test]
+    fn test_from_raw_locked_round_trip() {
+        let raw = Box::into_raw(Box::new(vec![1u8; 16]));
+
+        let mut secret = unsafe { SecretBox::from_raw_locked(raw) };
+
+        assert_eq!(secret.inner_secret.as_ptr(), raw);
+        assert!(secret.protection.is_none() && secret.slot.is_none());
+        assert_eq!(secret.is_locked(), lock::ENABLED);
+
+        secret.expose_secret_mut()[1] = 2;
+        let clone = secret.clone();
+        drop(secret);
+
+        assert_eq!(clone.expose_secret()[..2], [1, 2]);
+    }
+
+    /// Poll `future` to completion, waking it by polling again.
+    fn block_on<F: core::future::Future>(future: F) -> F::Output {
+        let mut context = core::task::Context::from_waker(core::task::Waker::noop());
+        let mut future = core::pin::pin!(future);
+
+        loop {
+            if let core::task::Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_secret_async() {
+        let secret = SecretBox::new_protected(Box::new([3u8; 16]));
+
+        let sum = block_on(secret.with_secret_async(async |secret| {
+            core::future::ready(()).await;
+            secret.iter().map(|&byte| byte as u32).sum::<u32>()
+        }));
+
+        assert_eq!(sum, 48);
+        assert_eq!(secret.expose_secret()[0], 3);
+    }
+
+    #[test]
+    fn test_clear_keeps_memory_locked() {
+        let calls = || lock::CALLS.with(Cell::get);
+
+        let mut buffer = SecretBox::try_zeroed(32).unwrap();
+        buffer.expose_secret_mut().fill(1);
+        let mut key = SecretBox::new_protected(Box::new([1u8; 32]));
+        let before = (calls(), buffer.with_secret_mut(vec_buffer));
+
+        buffer.clear();
+        key.clear();
+        assert!(buffer.expose_secret().is_empty());
+        assert_eq!(*key.expose_secret(), [0; 32]);
+
+        buffer.expose_secret_mut().extend_from_slice(&[2; 32]);
+        key.expose_secret_mut().fill(2);
+
+        assert_eq!((calls(), buffer.with_secret_mut(vec_buffer)), before);
+        assert_eq!(*buffer.expose_secret(), [2; 32]);
+        assert_eq!(*key.expose_secret(), [2; 32]);
+        assert!(!buffer.is_destroyed() && buffer.is_locked() == lock::ENABLED);
+    }
+
+    #[test]
+    fn test_leak_and_reclaim_keeps_pages_locked() {
+        let calls = || lock::CALLS.with(Cell::get);
+
+        let key = SecretBox::new(Box::new([7u8; 32])).with_debug_label("<key>");
+        let secret_ptr = key.inner_secret.as_ptr();
+        let before = calls();
+
+        let (ptr, len) = key.leak();
+        assert_eq!((ptr, len), (secret_ptr, 32));
+        unsafe { *ptr = [8; 32] };
+
+        let key = unsafe { SecretBox::reclaim(ptr, len) };
+        assert_eq!(calls(), before);
+        assert_eq!(*key.expose_secret(), [8; 32]);
+        assert_eq!(format!("{key:?}"), "SecretBox<[u8; 32]>([REDACTED])");
+        assert!(key.is_locked() == lock::ENABLED && !key.is_protected());
+    }
+
+    #[test]
+    fn test_leak_moves_protected_and_pooled_secrets() {
+        let pool = SecretPool::new(16, 1);
+        let secrets = [
+            SecretBox::new_protected(Box::new(1u64)),
+            SecretBox::new_guarded(Box::new(2u64)),
+            SecretBox::new_in(Box::new(3u64), &pool),
+        ];
+
+        for (expected, secret) in (1..).zip(secrets) {
+            let (ptr, len) = secret.leak();
+            assert_eq!(ptr as usize % page::page_size(), 0);
+            assert_eq!(unsafe { *ptr }, expected);
+
+            let secret = unsafe { SecretBox::reclaim(ptr, len) };
+            assert_eq!(*secret.expose_secret(), expected);
+        }
+
+        // The pooled secret gave its slot back.
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot leak a secret with a locked heap buffer")]
+    fn test_leak_with_locked_heap_panics() {
+        SecretBox::from_slice_zeroizing(&mut [1; 32]).leak();
+    }
+
+    #[test]
+    fn test_replace_with_relocks_heap() {
+        let mut secret = SecretBox::try_zeroed(4).unwrap();
+
+        secret.replace_with(|bytes| bytes.extend_from_slice(&[1; 4096]));
+
+        assert_heap_locked(&mut secret);
+    }
+
+    #[test]
+    fn test_replace_with_reallocating_leaves_freed_buffer_alone() {
+        let mut secret = SecretBox::try_zeroed(256 << 10).unwrap();
+        let calls = lock::CALLS.with(Cell::get);
+
+        secret.replace_with(|bytes| bytes.reserve(8 << 20));
+
+        // Only the new buffer is locked, the freed one is not unlocked.
+        assert_eq!(lock::CALLS.with(Cell::get), calls + 1);
+        assert_heap_locked(&mut secret);
+    }
+
+    #[test]
+    fn test_debug_label() {
+        let secret = SecretBox::new(Box::new(*b"hunter2")).with_debug_label("<password>");
+
+        let debug = format!("{secret:?} {:?}", secret.clone());
+        assert_eq!(debug.matches("(<password>)").count(), 2);
+        assert!(!debug.contains("hunter2") && !debug.contains("104"));
+    }
+
+    #[cfg(feature = "deref")]
+    #[test]
+    fn test_deref() {
+        let secret = SecretBox::new(Box::new([1u8, 2, 3]));
+        let protected = SecretBox::new_protected(Box::new(7u64));
+
+        assert_eq!(secret.iter().sum::<u8>(), 6);
+        assert_eq!(*protected + 1, 8);
+        assert_eq!(*protected.expose_secret(), 7);
+    }
+
+    #[cfg(not(feature = "deref"))]
+    #[test]
+    fn test_deref_is_opt_in() {
+        // The inherent constant only exists for `Deref` types, otherwise the blanket
+        // trait constant is picked.
+        trait NotDeref {
+            const DEREF: bool = false;
+        }
+        impl<T> NotDeref for T {}
+
+        struct Probe<T>(T);
+        impl<T: Deref> Probe<T> {
+            const DEREF: bool = true;
+        }
+
+        const {
+            assert!(Probe::<Box<u8>>::DEREF);
+            assert!(!Probe::<SecretBox<u8>>::DEREF);
+        }
+    }
+
+    #[cfg(feature = "mlock")]
+    #[test]
+    fn test_is_locked() {
+        assert!(SecretBox::new(Box::new(1u8)).is_locked());
+        assert!(SecretBox::new_protected(Box::new(1u8)).is_locked());
+    }
+
+    #[cfg(not(feature = "mlock"))]
+    #[test]
+    fn test_is_not_locked_without_mlock() {
+        assert!(!SecretBox::new(Box::new(1u8)).is_locked());
+        assert!(!SecretBox::new_protected(Box::new(1u8)).is_locked());
+    }
+
+    #[test]
+    fn test_destroy() {
+        let mut secret = SecretBox::try_zeroed(16).unwrap();
+        secret.expose_secret_mut().fill(0xaa);
+
+        secret.destroy();
+
+        assert!(secret.is_destroyed() && !secret.is_locked());
+        assert!(secret.locked_heap.is_none());
+        assert!(unsafe { secret.inner_secret.as_ref() }.is_empty());
+
+        secret.destroy();
+        secret.zeroize();
+        drop(secret);
+    }
+
+    #[test]
+    fn test_destroy_protected() {
+        let mut secret = SecretBox::new_protected(Box::new([0xaau8; 16]));
+
+        secret.destroy();
+
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| secret.with_secret(|_| ())));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_guarded_secret() {
+        let mut secret = SecretBox::new_guarded(Box::new([1u8; 100]));
+        secret.expose_secret_mut()[99] = 2;
+
+        let pages = secret.protection.as_ref().unwrap().pages();
+        let end = unsafe { pages.as_ptr().add(pages.len()) };
+        assert_eq!(secret.expose_secret().as_ptr_range().end, end.cast_const());
+
+        let clone = secret.clone();
+        assert!(clone.protection.as_ref().unwrap().pages().is_guarded());
+        assert_eq!(clone.expose_secret()[..], secret.expose_secret()[..]);
+    }
+
+    #[test]
+    fn test_guard_index() {
+        let mut secret = SecretBox::new_protected(Box::new(vec![1u8, 2, 3, 4]));
+
+        let guard = secret.expose_secret();
+        assert_eq!(guard[2], 3);
+        assert_eq!(&guard[1..3], [2, 3]);
+        drop(guard);
+
+        let mut guard = secret.expose_secret_mut();
+        guard[0] = 9;
+        guard[2..].fill(0);
+        assert_eq!(&guard[..], [9, 2, 0, 0]);
+    }
+
+    #[test]
+    fn test_transmute_into() {
+        struct Key([u8; 4]);
+
+        impl Zeroize for Key {
+            fn zeroize(&mut self) {
+                self.0.zeroize()
+            }
         }
+
+        let secret = SecretBox::new_protected(Box::new([1u8, 2, 3, 4]));
+        let ptr = secret.inner_secret.as_ptr().cast::<u8>();
+
+        let key = unsafe { secret.transmute_into::<Key>() }.unwrap();
+
+        assert_eq!(key.inner_secret.as_ptr().cast::<u8>(), ptr);
+        assert_eq!(key.with_secret(|key| key.0), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_transmute_into_rejects_mismatched_layout() {
+        let secret = SecretBox::new(Box::new([1u8; 4]));
+
+        let secret = unsafe { secret.transmute_into::<[u8; 8]>() }.unwrap_err();
+        let secret = unsafe { secret.transmute_into::<u32>() }.unwrap_err();
+        assert_eq!(*secret.expose_secret(), [1; 4]);
+
+        let heap = SecretBox::try_zeroed(4).unwrap();
+        assert!(unsafe { heap.transmute_into::<[usize; 3]>() }.is_err());
     }
 }
@@ -0,0 +1,190 @@
+//! Secrets that can only be exposed a bounded number of times.
+
+use crate::{ExposeSecret, SecretBox, SecretGuard, SecretGuardMut};
+use core::{
+    mem::ManuallyDrop,
+    ops::Deref,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+use zeroize::Zeroize;
+
+/// Secret that can be exposed at most a fixed number of times, e.g. a one-time token.
+///
+/// Once the limit is reached [`Self::expose_secret`] returns `None`, and the secret is
+/// zeroized as soon as the last guard of the final exposure is dropped. The counters
+/// are atomic, so it can be shared between threads, e.g. in an `Arc`.
+///
+/// # Examples
+///
+/// ```
+/// use shush::{LimitedSecret, SecretBox};
+///
+/// let token = LimitedSecret::new(SecretBox::new(Box::new(*b"one-time")), 1);
+///
+/// assert_eq!(&*token.expose_secret().unwrap(), b"one-time");
+/// assert!(token.expose_secret().is_none());
+/// ```
+pub struct LimitedSecret<S: Zeroize> {
+    secret: SecretBox<S>,
+    remaining: AtomicUsize,
+    /// Guards alive, plus callers in the middle of [`Self::expose_secret`].
+    live: AtomicUsize,
+    zeroized: AtomicBool,
+}
+
+// The last guard zeroizes the secret through a shared reference, possibly on another
+// thread than the one that created it.
+unsafe impl<S: Zeroize + Send + Sync> Sync for LimitedSecret<S> {}
+
+impl<S: Zeroize> LimitedSecret<S> {
+    /// Allow `secret` to be exposed `limit` times.
+    pub fn new(secret: SecretBox<S>, limit: usize) -> Self {
+        let limited = Self {
+            secret,
+            remaining: AtomicUsize::new(limit),
+            live: AtomicUsize::new(0),
+            zeroized: AtomicBool::new(false),
+        };
+
+        if limit == 0 {
+            limited.zeroize_secret();
+        }
+
+        limited
+    }
+
+    /// Expose the secret as non-mutable, or return `None` once the limit is reached.
+    pub fn expose_secret(&self) -> Option<LimitedGuard<'_, S>> {
+        // Count this caller as live before taking an exposure, so the secret can't be
+        // zeroized between the two.
+        self.live.fetch_add(1, Ordering::SeqCst);
+
+        let exposed = self
+            .remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |remaining| {
+                remaining.checked_sub(1)
+            })
+            .is_ok();
+
+        if !exposed {
+            self.release();
+            return None;
+        }
+
+        Some(LimitedGuard {
+            guard: ManuallyDrop::new(self.secret.expose_secret()),
+            limited: self,
+        })
+    }
+
+    /// Number of exposures left.
+    pub fn remaining(&self) -> usize {
+        self.remaining.load(Ordering::SeqCst)
+    }
+
+    /// Unregister a live caller, zeroizing the secret if it was the last one and no
+    /// exposures are left.
+    fn release(&self) {
+        if self.live.fetch_sub(1, Ordering::SeqCst) == 1
+            && self.remaining.load(Ordering::SeqCst) == 0
+        {
+            self.zeroize_secret();
+        }
+    }
+
+    fn zeroize_secret(&self) {
+        if self.zeroized.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // No guard is alive and none can be created anymore, so nothing else can access
+        // the secret.
+        let secret = unsafe { &mut *self.secret.inner_secret.as_ptr() };
+        SecretGuardMut::with_protection(secret, self.secret.protection.as_ref()).zeroize();
+    }
+}
+
+/// Guard returned by [`LimitedSecret::expose_secret`].
+pub struct LimitedGuard<'a, S: Zeroize> {
+    guard: ManuallyDrop<SecretGuard<'a, S>>,
+    limited: &'a LimitedSecret<S>,
+}
+
+impl<S: Zeroize> Deref for LimitedGuard<'_, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.guard
+    }
+}
+
+impl<S: Zeroize> Drop for LimitedGuard<'_, S> {
+    fn drop(&mut self) {
+        // Conceal the pages first, zeroizing has to make them writable again.
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        self.limited.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use std::thread;
+
+    fn peek<S: Zeroize + Copy>(limited: &LimitedSecret<S>) -> S {
+        limited.secret.with_secret(|secret| *secret)
+    }
+
+    #[test]
+    fn test_limited_secret_exposes_up_to_limit() {
+        let limited = LimitedSecret::new(SecretBox::new(Box::new([7u8; 4])), 2);
+
+        assert_eq!(*limited.expose_secret().unwrap(), [7; 4]);
+        assert_eq!(limited.remaining(), 1);
+
+        let last = limited.expose_secret().unwrap();
+        assert!(limited.expose_secret().is_none());
+        assert_eq!(*last, [7; 4]);
+
+        drop(last);
+        assert!(limited.expose_secret().is_none());
+        assert_eq!(peek(&limited), [0; 4]);
+    }
+
+    #[test]
+    fn test_limited_secret_protected() {
+        let limited = LimitedSecret::new(SecretBox::new_protected(Box::new(9u64)), 1);
+
+        assert_eq!(*limited.expose_secret().unwrap(), 9);
+        assert_eq!(peek(&limited), 0);
+    }
+
+    #[test]
+    fn test_limited_secret_zero_limit() {
+        let limited = LimitedSecret::new(SecretBox::new_protected(Box::new(9u64)), 0);
+
+        assert!(limited.expose_secret().is_none());
+        assert_eq!(peek(&limited), 0);
+    }
+
+    #[test]
+    fn test_limited_secret_across_threads() {
+        let limited = LimitedSecret::new(SecretBox::new(Box::new(5u32)), 3);
+
+        let exposed: usize = thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| limited.expose_secret().map(|guard| *guard)))
+                .collect();
+
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap())
+                .inspect(|&value| assert_eq!(value, 5))
+                .count()
+        });
+
+        assert_eq!(exposed, 3);
+        assert_eq!(peek(&limited), 0);
+    }
+}
@@ -0,0 +1,200 @@
+//! Platform abstraction over locking the memory of a secret.
+//!
+//! On Unix this is `mlock(2)`/`munlock(2)`, on Windows `VirtualLock`/`VirtualUnlock`.
+//! Without the `mlock` feature locking is a no-op that always succeeds.
+
+use crate::LockError;
+
+/// Whether [`lock`] actually locks memory, it is a no-op without the `mlock` feature.
+pub(crate) const ENABLED: bool = cfg!(feature = "mlock");
+
+#[cfg(test)]
+std::thread_local! {
+    /// Calls to [`lock`] and [`unlock`] made by the current thread, so tests can check
+    /// that something doesn't lock or unlock memory.
+    pub(crate) static CALLS: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+/// Lock `len` bytes starting at `ptr` so they are never swapped to disk.
+///
+/// # Safety
+///
+/// `ptr` must point to an allocation of at least `len` bytes.
+pub(crate) unsafe fn lock(ptr: *mut u8, len: usize) -> Result<(), LockError> {
+    #[cfg(test)]
+    CALLS.with(|calls| calls.set(calls.get() + 1));
+
+    if !imp::lock(ptr, len) {
+        return Err(LockError::last_os_error(len));
+    }
+
+    #[cfg(feature = "prefault")]
+    prefault(ptr, len);
+
+    Ok(())
+}
+
+/// Same as [`lock`], but only lock pages once they are touched, so pages that are
+/// never accessed don't take up locked memory.
+///
+/// This is `mlock2(MLOCK_ONFAULT)` on Linux. Kernels without it, before 4.4, and other
+/// platforms fall back to locking the pages upfront. Pages are not prefaulted either
+/// way.
+///
+/// # Safety
+///
+/// `ptr` must point to an allocation of at least `len` bytes.
+pub(crate) unsafe fn lock_on_fault(ptr: *mut u8, len: usize) -> Result<(), LockError> {
+    #[cfg(test)]
+    CALLS.with(|calls| calls.set(calls.get() + 1));
+
+    if !imp::lock_on_fault(ptr, len) {
+        return Err(LockError::last_os_error(len));
+    }
+
+    Ok(())
+}
+
+/// Handle a lock failure in a constructor that can't return it, by panicking with
+/// `err`, or aborting the process without unwinding with the `abort-on-lock-failure`
+/// feature.
+pub(crate) fn failed<T>(err: LockError) -> T {
+    #[cfg(feature = "abort-on-lock-failure")]
+    {
+        std::eprintln!("Unable to mlock variable: {err}");
+        std::process::abort();
+    }
+
+    #[cfg(not(feature = "abort-on-lock-failure"))]
+    panic!("Unable to mlock variable: {err}");
+}
+
+/// Write-touch every page holding `len` bytes at `ptr` so they are resident now, some
+/// systems only fault locked pages in on first access.
+///
+/// # Safety
+///
+/// `ptr` must point to an allocation of at least `len` bytes that nothing else accesses
+/// concurrently.
+#[cfg(feature = "prefault")]
+unsafe fn prefault(ptr: *mut u8, len: usize) {
+    let page_size = crate::page::page_size();
+    let mut offset = 0;
+
+    while offset < len {
+        let byte = ptr.add(offset);
+        byte.write_volatile(byte.read_volatile());
+
+        // Move to the start of the next page.
+        offset += page_size - (byte as usize & (page_size - 1));
+    }
+}
+
+/// Unlock memory previously locked with [`lock`], returning whether it succeeded.
+///
+/// # Safety
+///
+/// `ptr` must point to an allocation of at least `len` bytes.
+pub(crate) unsafe fn unlock(ptr: *mut u8, len: usize) -> bool {
+    #[cfg(test)]
+    CALLS.with(|calls| calls.set(calls.get() + 1));
+
+    imp::unlock(ptr, len)
+}
+
+#[cfg(all(feature = "mlock", not(any(unix, windows))))]
+compile_error!(concat!(
+    "the `mlock` feature is only supported on Unix and Windows, ",
+    "build with `--no-default-features`"
+));
+
+#[cfg(all(feature = "mlock", unix))]
+mod imp {
+    pub(super) unsafe fn lock(ptr: *mut u8, len: usize) -> bool {
+        libc::mlock(ptr.cast(), len) == 0
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(super) unsafe fn lock_on_fault(ptr: *mut u8, len: usize) -> bool {
+        // Through `syscall` since the libc wrapper is missing from older C libraries.
+        if libc::syscall(libc::SYS_mlock2, ptr, len, libc::MLOCK_ONFAULT) == 0 {
+            return true;
+        }
+
+        // Kernels without `mlock2` return `ENOSYS`, and `EINVAL` if they don't know the
+        // flag.
+        let errno = std::io::Error::last_os_error().raw_os_error();
+        matches!(errno, Some(libc::ENOSYS | libc::EINVAL)) && lock(ptr, len)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(super) unsafe fn lock_on_fault(ptr: *mut u8, len: usize) -> bool {
+        lock(ptr, len)
+    }
+
+    pub(super) unsafe fn unlock(ptr: *mut u8, len: usize) -> bool {
+        libc::munlock(ptr.cast(), len) == 0
+    }
+}
+
+#[cfg(all(feature = "mlock", windows))]
+mod imp {
+    use windows_sys::Win32::{
+        Foundation::{GetLastError, ERROR_WORKING_SET_QUOTA},
+        System::{
+            Memory::{VirtualLock, VirtualUnlock},
+            Threading::{GetCurrentProcess, GetProcessWorkingSetSize, SetProcessWorkingSetSize},
+        },
+    };
+
+    pub(super) unsafe fn lock(ptr: *mut u8, len: usize) -> bool {
+        // `VirtualLock` rejects empty regions, there is nothing to lock anyway.
+        if len == 0 {
+            return true;
+        }
+
+        if VirtualLock(ptr.cast(), len) != 0 {
+            return true;
+        }
+
+        // Windows only lets a process lock as many pages as fit in its minimum working
+        // set, so grow it by the size of the region and try once more.
+        GetLastError() == ERROR_WORKING_SET_QUOTA
+            && grow_working_set(len)
+            && VirtualLock(ptr.cast(), len) != 0
+    }
+
+    pub(super) unsafe fn lock_on_fault(ptr: *mut u8, len: usize) -> bool {
+        lock(ptr, len)
+    }
+
+    pub(super) unsafe fn unlock(ptr: *mut u8, len: usize) -> bool {
+        len == 0 || VirtualUnlock(ptr.cast(), len) != 0
+    }
+
+    unsafe fn grow_working_set(len: usize) -> bool {
+        let process = GetCurrentProcess();
+        let (mut min, mut max) = (0, 0);
+
+        if GetProcessWorkingSetSize(process, &mut min, &mut max) == 0 {
+            return false;
+        }
+
+        SetProcessWorkingSetSize(process, min.saturating_add(len), max.saturating_add(len)) != 0
+    }
+}
+
+#[cfg(not(feature = "mlock"))]
+mod imp {
+    pub(super) unsafe fn lock(_ptr: *mut u8, _len: usize) -> bool {
+        true
+    }
+
+    pub(super) unsafe fn lock_on_fault(_ptr: *mut u8, _len: usize) -> bool {
+        true
+    }
+
+    pub(super) unsafe fn unlock(_ptr: *mut u8, _len: usize) -> bool {
+        true
+    }
+}
@@ -0,0 +1,175 @@
+//! Named secrets, each in its own locked allocation.
+
+use crate::{ExposeSecret, SecretBox, SecretGuard, SecretGuardMut};
+use alloc::collections::BTreeMap;
+use core::{
+    any,
+    borrow::Borrow,
+    fmt::{self, Debug},
+};
+use zeroize::Zeroize;
+
+/// Map of named secrets such as a credential store, each kept in its own
+/// [`SecretBox`].
+///
+/// Looking up a key only exposes that one secret, the others stay concealed. Every
+/// secret is zeroized and unlocked when it is removed or the map is dropped. The keys
+/// are not secret, they are stored and printed in `Debug` as they are.
+///
+/// # Examples
+///
+/// ```
+/// use shush::{SecretBox, SecretMap};
+///
+/// let mut credentials = SecretMap::new();
+/// credentials.insert("github", SecretBox::new(Box::new(*b"ghp_token")));
+/// credentials.insert("aws", SecretBox::new_protected(Box::new(*b"aws_token")));
+///
+/// assert_eq!(&*credentials.expose("github").unwrap(), b"ghp_token");
+/// assert!(credentials.expose("gitlab").is_none());
+/// ```
+pub struct SecretMap<K, S: Zeroize>(BTreeMap<K, SecretBox<S>>);
+
+impl<K: Ord, S: Zeroize> SecretMap<K, S> {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// Insert `secret` under `key`, returning the secret it replaces if any.
+    ///
+    /// The secret keeps its locked allocation, so it can be created with whichever
+    /// constructor fits, such as [`SecretBox::new_protected`].
+    pub fn insert(&mut self, key: K, secret: SecretBox<S>) -> Option<SecretBox<S>> {
+        self.0.insert(key, secret)
+    }
+
+    /// Expose the secret under `key` as non-mutable, or return `None` if there is none.
+    pub fn expose<Q>(&self, key: &Q) -> Option<SecretGuard<'_, S>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.0.get(key).map(SecretBox::expose_secret)
+    }
+
+    /// Expose the secret under `key` as mutable, or return `None` if there is none.
+    pub fn expose_mut<Q>(&mut self, key: &Q) -> Option<SecretGuardMut<'_, S>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.0.get_mut(key).map(SecretBox::expose_secret_mut)
+    }
+
+    /// Remove the secret under `key` and return it, keeping its locked allocation.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<SecretBox<S>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.0.remove(key)
+    }
+
+    /// Whether there is a secret under `key`, without exposing it.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.0.contains_key(key)
+    }
+
+    /// Keys of the map, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.0.keys()
+    }
+
+    /// Number of secrets in the map.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the map holds no secret.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Ord, S: Zeroize> Default for SecretMap<K, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Debug, S: Zeroize> Debug for SecretMap<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretMap<{}>", any::type_name::<S>())?;
+        f.debug_list().entries(self.0.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lock;
+    use alloc::{boxed::Box, string::String};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static ZEROIZED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted([u8; 16]);
+
+    impl Zeroize for Counted {
+        fn zeroize(&mut self) {
+            ZEROIZED.fetch_add(1, Ordering::SeqCst);
+            self.0.zeroize();
+        }
+    }
+
+    #[test]
+    fn test_secret_map_insert_and_expose() {
+        let mut map = SecretMap::new();
+        assert!(map.insert(String::from("a"), SecretBox::new(Box::new([1u8; 4]))).is_none());
+        map.insert(String::from("b"), SecretBox::new_protected(Box::new([2u8; 4])));
+
+        let old = map.insert(String::from("a"), SecretBox::new(Box::new([3u8; 4])));
+        assert_eq!(*old.unwrap().expose_secret(), [1; 4]);
+
+        map.expose_mut("b").unwrap()[0] = 4;
+        assert_eq!(*map.expose("a").unwrap(), [3; 4]);
+        assert_eq!(*map.expose("b").unwrap(), [4, 2, 2, 2]);
+        assert!(map.expose("c").is_none());
+        assert!(map.contains_key("a") && !map.contains_key("c"));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["a", "b"]);
+
+        assert_eq!(*map.remove("a").unwrap().expose_secret(), [3; 4]);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_secret_map_drop_cleans_up_every_entry() {
+        let mut map = SecretMap::new();
+        for key in 0..3 {
+            map.insert(key, SecretBox::new(Box::new(Counted([9; 16]))));
+        }
+        let calls = lock::CALLS.with(|calls| calls.get());
+
+        drop(map);
+
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 3);
+        // One unlock per entry.
+        assert_eq!(lock::CALLS.with(|calls| calls.get()), calls + 3);
+    }
+
+    #[test]
+    fn test_secret_map_debug_only_shows_keys() {
+        let mut map = SecretMap::new();
+        map.insert("github", SecretBox::new(Box::new(*b"ghp_token")));
+
+        let debug = format!("{map:?}");
+
+        assert_eq!(debug, "SecretMap<[u8; 9]>[\"github\"]");
+        assert!(!debug.contains("ghp_token"));
+    }
+}
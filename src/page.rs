@@ -0,0 +1,307 @@
+//! Page-aligned allocations whose access can be changed with `mprotect(2)`.
+//!
+//! On Windows access is changed with `VirtualProtect`. On targets without either,
+//! changing access is a no-op.
+
+use alloc::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use core::{
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(test)]
+std::thread_local! {
+    /// Calls to [`Pages::protect`] made by the current thread, so tests can check that
+    /// something doesn't change the access of pages.
+    pub(crate) static PROTECTS: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+/// Size of a memory page on this system.
+pub(crate) fn page_size() -> usize {
+    // Zero until the first call, racing callers all store the same value.
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+    match PAGE_SIZE.load(Ordering::Relaxed) {
+        0 => {
+            let page_size = imp::page_size();
+            PAGE_SIZE.store(page_size, Ordering::Relaxed);
+            page_size
+        }
+        page_size => page_size,
+    }
+}
+
+/// Access allowed to a range of pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Prot {
+    NoAccess,
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Zeroed allocation spanning whole pages and starting on a page boundary, so no
+/// other value shares its pages.
+///
+/// A guarded allocation has an extra inaccessible page right before and after it,
+/// which are not part of `ptr` and `len`.
+pub(crate) struct Pages {
+    ptr: NonNull<u8>,
+    len: usize,
+    guarded: bool,
+    /// Whether the pages are huge pages mapped with `mmap`, see [`Self::alloc_huge`].
+    #[cfg(all(target_os = "linux", feature = "std"))]
+    huge: bool,
+}
+
+// `Pages` owns its memory exclusively, like a `Box<[u8]>`.
+unsafe impl Send for Pages {}
+unsafe impl Sync for Pages {}
+
+impl Pages {
+    /// Allocate enough whole pages to hold `size` bytes, and at least one page.
+    pub(crate) fn alloc(size: usize) -> Self {
+        Self::alloc_with(size, false)
+    }
+
+    /// Same as [`Self::alloc`], with an inaccessible guard page on each side so any
+    /// access just before or past the allocation faults.
+    ///
+    /// On targets without `mprotect` or `VirtualProtect` the guard pages are accessible.
+    pub(crate) fn alloc_guarded(size: usize) -> Self {
+        Self::alloc_with(size, true)
+    }
+
+    fn alloc_with(size: usize, guarded: bool) -> Self {
+        let page_size = page_size();
+        let len = size.max(1).next_multiple_of(page_size);
+        let layout = Self::layout(len, guarded);
+
+        let base = unsafe { alloc_zeroed(layout) };
+        let base = NonNull::new(base).unwrap_or_else(|| handle_alloc_error(layout));
+
+        if !guarded {
+            return Self {
+                ptr: base,
+                len,
+                guarded,
+                #[cfg(all(target_os = "linux", feature = "std"))]
+                huge: false,
+            };
+        }
+
+        // Best-effort like changing access elsewhere, the allocation is still usable if
+        // the guard pages stay accessible.
+        unsafe {
+            imp::protect(base.as_ptr(), page_size, Prot::NoAccess);
+            imp::protect(base.as_ptr().add(page_size + len), page_size, Prot::NoAccess);
+        }
+
+        Self {
+            ptr: unsafe { base.add(page_size) },
+            len,
+            guarded,
+            #[cfg(all(target_os = "linux", feature = "std"))]
+            huge: false,
+        }
+    }
+
+    /// Map enough whole huge pages to hold `size` bytes, and at least one, with
+    /// `mmap(MAP_HUGETLB)`.
+    ///
+    /// Returns `None` if the system has no huge page to spare, which is the default
+    /// until some are reserved through `vm.nr_hugepages`, and on other platforms than
+    /// Linux.
+    #[cfg(all(target_os = "linux", feature = "std"))]
+    pub(crate) fn alloc_huge(size: usize) -> Option<Self> {
+        let len = size.max(1).checked_next_multiple_of(huge_page_size()?)?;
+        let ptr = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_HUGETLB,
+                -1,
+                0,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return None;
+        }
+
+        // Anonymous mappings are zeroed, like the other allocations.
+        Some(Self {
+            ptr: NonNull::new(ptr.cast())?,
+            len,
+            guarded: false,
+            huge: true,
+        })
+    }
+
+    /// See the Linux version.
+    #[cfg(not(all(target_os = "linux", feature = "std")))]
+    pub(crate) fn alloc_huge(_size: usize) -> Option<Self> {
+        None
+    }
+
+    /// Take back pages handed out with [`core::mem::forget`], that were allocated with
+    /// [`Self::alloc`] to hold `size` bytes.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be the start of such an allocation, that nothing else owns anymore.
+    pub(crate) unsafe fn from_raw(ptr: *mut u8, size: usize) -> Self {
+        Self {
+            ptr: NonNull::new_unchecked(ptr),
+            len: size.max(1).next_multiple_of(page_size()),
+            guarded: false,
+            #[cfg(all(target_os = "linux", feature = "std"))]
+            huge: false,
+        }
+    }
+
+    /// Start of the allocation.
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    /// Length of the allocation, a multiple of the page size.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the allocation has guard pages, see [`Self::alloc_guarded`].
+    pub(crate) fn is_guarded(&self) -> bool {
+        self.guarded
+    }
+
+    /// Whether the allocation is made of huge pages, see [`Self::alloc_huge`].
+    pub(crate) fn is_huge(&self) -> bool {
+        #[cfg(all(target_os = "linux", feature = "std"))]
+        return self.huge;
+
+        #[cfg(not(all(target_os = "linux", feature = "std")))]
+        false
+    }
+
+    /// Change the access allowed to the pages, returning whether it succeeded.
+    ///
+    /// # Safety
+    ///
+    /// No reference into the pages may be used in a way that `prot` forbids.
+    pub(crate) unsafe fn protect(&self, prot: Prot) -> bool {
+        #[cfg(test)]
+        PROTECTS.with(|protects| protects.set(protects.get() + 1));
+
+        imp::protect(self.as_ptr(), self.len, prot)
+    }
+
+    /// Layout of an allocation of `len` bytes, plus its guard pages if `guarded`.
+    fn layout(len: usize, guarded: bool) -> Layout {
+        let guards = if guarded { 2 * page_size() } else { 0 };
+        let size = len.checked_add(guards).expect("page-aligned layout overflows usize");
+
+        Layout::from_size_align(size, page_size()).expect("page-aligned layout overflows isize")
+    }
+}
+
+impl Drop for Pages {
+    fn drop(&mut self) {
+        #[cfg(all(target_os = "linux", feature = "std"))]
+        if self.huge {
+            unsafe { libc::munmap(self.as_ptr().cast(), self.len) };
+            return;
+        }
+
+        let layout = Self::layout(self.len, self.guarded);
+        let base = match self.guarded {
+            true => unsafe { self.as_ptr().sub(page_size()) },
+            false => self.as_ptr(),
+        };
+
+        // The allocator may write its own bookkeeping into freed memory.
+        unsafe {
+            imp::protect(base, layout.size(), Prot::ReadWrite);
+            dealloc(base, layout);
+        }
+    }
+}
+
+/// Make `len` bytes of pages at `ptr` readable and writable, for pages whose [`Pages`]
+/// can't be reached, returning whether it succeeded.
+///
+/// # Safety
+///
+/// `ptr` must be the start of a page, followed by at least `len` bytes of allocation.
+#[cfg(feature = "registry")]
+pub(crate) unsafe fn unprotect(ptr: *mut u8, len: usize) -> bool {
+    imp::protect(ptr, len, Prot::ReadWrite)
+}
+
+/// Default size of a huge page, from `Hugepagesize` in `/proc/meminfo`.
+#[cfg(all(target_os = "linux", feature = "std"))]
+fn huge_page_size() -> Option<usize> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    // e.g. `Hugepagesize:       2048 kB`
+    let kb = meminfo.lines().find_map(|line| line.strip_prefix("Hugepagesize:"))?;
+
+    kb.trim().strip_suffix("kB")?.trim().parse::<usize>().ok()?.checked_mul(1024)
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::Prot;
+
+    pub(super) fn page_size() -> usize {
+        unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+    }
+
+    pub(super) unsafe fn protect(ptr: *mut u8, len: usize, prot: Prot) -> bool {
+        let prot = match prot {
+            Prot::NoAccess => libc::PROT_NONE,
+            Prot::ReadOnly => libc::PROT_READ,
+            Prot::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+        };
+
+        libc::mprotect(ptr.cast(), len, prot) == 0
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::Prot;
+    use windows_sys::Win32::System::{
+        Memory::{VirtualProtect, PAGE_NOACCESS, PAGE_READONLY, PAGE_READWRITE},
+        SystemInformation::GetSystemInfo,
+    };
+
+    pub(super) fn page_size() -> usize {
+        let mut info = unsafe { core::mem::zeroed() };
+        unsafe { GetSystemInfo(&mut info) };
+        info.dwPageSize as usize
+    }
+
+    pub(super) unsafe fn protect(ptr: *mut u8, len: usize, prot: Prot) -> bool {
+        let prot = match prot {
+            Prot::NoAccess => PAGE_NOACCESS,
+            Prot::ReadOnly => PAGE_READONLY,
+            Prot::ReadWrite => PAGE_READWRITE,
+        };
+        let mut old = 0;
+
+        VirtualProtect(ptr.cast(), len, prot, &mut old) != 0
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use super::Prot;
+
+    pub(super) fn page_size() -> usize {
+        4096
+    }
+
+    pub(super) unsafe fn protect(_ptr: *mut u8, _len: usize, _prot: Prot) -> bool {
+        true
+    }
+}
@@ -0,0 +1,263 @@
+//! Pools of locked memory shared by many small secrets.
+
+use crate::{advise_locked, arena::ArenaSlot, lock, page::Pages, release_locked, LockError};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    slice,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use zeroize::Zeroize;
+
+/// Alignment of every slot, secrets aligned past it are not pooled.
+pub(crate) const SLOT_ALIGN: usize = 16;
+
+/// Slab of locked memory split into fixed-size slots for small secrets, see
+/// [`SecretBox::new_in`](crate::SecretBox::new_in).
+///
+/// The slab is locked once when the pool is created and unlocked once when the pool and
+/// every secret in it are dropped, instead of one `mlock` and `munlock` call per secret.
+/// Since the slab stays locked as a whole, secrets sharing its pages never unlock each
+/// other. A slot is zeroized when its secret is dropped, before it is handed out again.
+///
+/// Cloning a pool only shares the same slab.
+///
+/// # Examples
+///
+/// ```
+/// use shush::{ExposeSecret, SecretBox, SecretPool};
+///
+/// let pool = SecretPool::new(32, 64);
+///
+/// for i in 0..1000u64 {
+///     let nonce = SecretBox::new_in(Box::new(i), &pool);
+///     assert_eq!(*nonce.expose_secret(), i);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SecretPool(Arc<Slab>);
+
+struct Slab {
+    pages: Pages,
+    slot_size: usize,
+    used: Box<[AtomicBool]>,
+}
+
+impl SecretPool {
+    /// Lock a slab of `slots` slots, each holding a secret of up to `slot_size` bytes.
+    ///
+    /// The slot size is rounded up to a multiple of 16 bytes, the alignment of every
+    /// slot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slab cannot be locked, see [`Self::try_new`] for a fallible
+    /// version.
+    pub fn new(slot_size: usize, slots: usize) -> Self {
+        Self::try_new(slot_size, slots).unwrap_or_else(lock::failed)
+    }
+
+    /// Same as [`Self::new`], but returns an error if the slab cannot be locked.
+    pub fn try_new(slot_size: usize, slots: usize) -> Result<Self, LockError> {
+        let slot_size = slot_size.max(1).next_multiple_of(SLOT_ALIGN);
+        let pages = Pages::alloc(slot_size.checked_mul(slots).expect("pool size overflows usize"));
+
+        unsafe {
+            lock::lock(pages.as_ptr(), pages.len())?;
+            advise_locked(pages.as_ptr(), pages.len());
+        }
+
+        Ok(Self(Arc::new(Slab {
+            pages,
+            slot_size,
+            used: (0..slots).map(|_| AtomicBool::new(false)).collect::<Vec<_>>().into(),
+        })))
+    }
+
+    /// Size in bytes of every slot.
+    pub fn slot_size(&self) -> usize {
+        self.0.slot_size
+    }
+
+    /// Number of free slots.
+    pub fn available(&self) -> usize {
+        self.0.used.iter().filter(|used| !used.load(Ordering::Relaxed)).count()
+    }
+
+    /// Claim a free slot for a secret of `size` bytes aligned to `align`, if it fits.
+    pub(crate) fn claim(&self, size: usize, align: usize) -> Option<PoolSlot> {
+        if size > self.0.slot_size || align > SLOT_ALIGN {
+            return None;
+        }
+
+        let index = self.0.used.iter().position(|used| {
+            used.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        })?;
+
+        Some(PoolSlot {
+            pool: self.clone(),
+            index,
+        })
+    }
+}
+
+impl Debug for SecretPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretPool")
+            .field("slot_size", &self.0.slot_size)
+            .field("slots", &self.0.used.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Slab {
+    fn drop(&mut self) {
+        // The pages are freed once they are dropped right after.
+        unsafe { release_locked(self.pages.as_ptr(), self.pages.len()) };
+    }
+}
+
+/// Memory of a secret carved out of locked memory shared with other secrets, which
+/// stays locked when the secret is dropped.
+pub(crate) enum Slot {
+    Pool(PoolSlot),
+    Arena(ArenaSlot),
+}
+
+impl Slot {
+    /// Start of the slot.
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        match self {
+            Self::Pool(slot) => slot.as_ptr(),
+            Self::Arena(slot) => slot.as_ptr(),
+        }
+    }
+}
+
+/// Slot of a pool holding one secret, zeroized and freed on drop.
+pub(crate) struct PoolSlot {
+    pub(crate) pool: SecretPool,
+    index: usize,
+}
+
+impl PoolSlot {
+    /// Start of the slot.
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        unsafe { self.pool.0.pages.as_ptr().add(self.index * self.pool.0.slot_size) }
+    }
+}
+
+impl Drop for PoolSlot {
+    fn drop(&mut self) {
+        // Wipe whatever `S` left behind, e.g. the pointer of a `Vec`, before the slot is
+        // handed out again.
+        unsafe { slice::from_raw_parts_mut(self.as_ptr(), self.pool.0.slot_size) }.zeroize();
+
+        self.pool.0.used[self.index].store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExposeSecret, SecretBox};
+
+    #[test]
+    fn test_pooled_secrets() {
+        let pool = SecretPool::new(32, 4);
+        assert_eq!(pool.available(), 4);
+
+        let a = SecretBox::new_in(Box::new([1u8; 32]), &pool);
+        let b = SecretBox::new_in(Box::new(2u64), &pool);
+
+        assert_eq!(pool.available(), 2);
+        assert!(a.slot.is_some() && b.slot.is_some());
+        assert_eq!(a.is_locked(), lock::ENABLED);
+        assert_eq!(*a.expose_secret(), [1; 32]);
+        assert_eq!(*b.expose_secret(), 2);
+
+        drop(a);
+        assert_eq!(pool.available(), 3);
+        assert_eq!(*b.expose_secret(), 2);
+    }
+
+    #[test]
+    fn test_slot_zeroized_on_return() {
+        let pool = SecretPool::new(64, 1);
+
+        let secret = SecretBox::new_in(Box::new(b"hunter2".to_vec()), &pool);
+        let slot_ptr = secret.slot.as_ref().unwrap().as_ptr();
+        drop(secret);
+
+        // The slab outlives the secret, so its slot can still be read.
+        let slot = unsafe { slice::from_raw_parts(slot_ptr, pool.slot_size()) };
+        assert!(slot.iter().all(|&byte| byte == 0));
+
+        let reused = SecretBox::new_in(Box::new([3u8; 64]), &pool);
+        assert_eq!(reused.slot.as_ref().unwrap().as_ptr(), slot_ptr);
+    }
+
+    #[test]
+    fn test_pooled_secret_zeroized_without_unlock() {
+        use core::sync::atomic::AtomicUsize;
+
+        static ZEROIZED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Key([u8; 16]);
+
+        impl Zeroize for Key {
+            fn zeroize(&mut self) {
+                ZEROIZED.fetch_add(1, Ordering::SeqCst);
+                self.0.zeroize();
+            }
+        }
+
+        let pool = SecretPool::new(16, 2);
+        let secret = SecretBox::new_in(Box::new(Key([7; 16])), &pool);
+        let neighbour = SecretBox::new_in(Box::new([8u8; 16]), &pool);
+
+        // Pooled secrets are never unlocked on their own, the pool stays locked.
+        drop(secret);
+
+        assert_eq!(ZEROIZED.load(Ordering::SeqCst), 1);
+        assert_eq!(neighbour.is_locked(), lock::ENABLED);
+        assert_eq!(*neighbour.expose_secret(), [8; 16]);
+    }
+
+    #[test]
+    fn test_unpooled_fallback() {
+        let pool = SecretPool::new(16, 1);
+
+        let too_big = SecretBox::new_in(Box::new([1u8; 17]), &pool);
+        let first = SecretBox::new_in(Box::new(1u128), &pool);
+        let exhausted = SecretBox::new_in(Box::new(2u128), &pool);
+
+        assert!(too_big.slot.is_none() && too_big.protection.is_some());
+        assert!(first.slot.is_some());
+        assert!(exhausted.slot.is_none());
+        assert_eq!(*exhausted.expose_secret(), 2);
+    }
+
+    #[test]
+    fn test_pooled_clone_stays_in_pool() {
+        let pool = SecretPool::new(32, 2);
+        let secret = SecretBox::new_in(Box::new([5u8; 32]), &pool);
+
+        let clone = secret.clone();
+
+        assert!(clone.slot.is_some());
+        assert_eq!(pool.available(), 0);
+        assert_eq!(*clone.expose_secret(), [5; 32]);
+    }
+
+    #[test]
+    fn test_secret_outlives_pool_handle() {
+        let pool = SecretPool::new(32, 2);
+        let secret = SecretBox::new_in(Box::new([9u8; 32]), &pool);
+
+        drop(pool);
+
+        assert_eq!(*secret.expose_secret(), [9; 32]);
+    }
+}
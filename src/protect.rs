@@ -0,0 +1,174 @@
+//! Keeping the pages of a secret inaccessible while no guard exposes it.
+
+use crate::{
+    page::{Pages, Prot},
+    registry,
+};
+use core::{
+    hint,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// Pages of a protected secret together with the number of guards exposing it.
+///
+/// The pages are only accessible while at least one guard is alive, the first guard
+/// makes them accessible and the last one to drop makes them inaccessible again.
+pub(crate) struct Protection {
+    pages: Pages,
+    guards: AtomicUsize,
+    /// Set once the pages were made accessible for good, see [`Self::unprotected`].
+    disabled: AtomicBool,
+    /// Whether the pages were made inaccessible by [`Self::new`], copies of the secret
+    /// are protected the same way.
+    protected: bool,
+    /// Length of the secret recorded by [`Self::len`], or [`UNRECORDED`].
+    len: AtomicUsize,
+    /// [`registry::wipes`] when `len` was recorded, `wipe_all` bypasses the guards.
+    len_wipes: AtomicUsize,
+    // Held while the guard count and the access of the pages change together. A spin
+    // lock keeps this working without `std`, and it is only held for one `mprotect`.
+    busy: AtomicBool,
+}
+
+/// [`Protection::len`] has to measure the secret again.
+const UNRECORDED: usize = usize::MAX;
+
+impl Protection {
+    /// Take over `pages`, making them inaccessible.
+    pub(crate) fn new(pages: Pages) -> Self {
+        unsafe { pages.protect(Prot::NoAccess) };
+
+        Self::with_disabled(pages, false)
+    }
+
+    /// Take over `pages` without protecting them, they stay readable and writable.
+    ///
+    /// This is for secrets that only need to live in pages of their own, so locking
+    /// and unlocking them never affects another secret.
+    pub(crate) fn unprotected(pages: Pages) -> Self {
+        Self::with_disabled(pages, true)
+    }
+
+    fn with_disabled(pages: Pages, disabled: bool) -> Self {
+        Self {
+            pages,
+            guards: AtomicUsize::new(0),
+            disabled: AtomicBool::new(disabled),
+            protected: !disabled,
+            len: AtomicUsize::new(UNRECORDED),
+            len_wipes: AtomicUsize::new(0),
+            busy: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn pages(&self) -> &Pages {
+        &self.pages
+    }
+
+    /// Whether the pages were created protected, even if protection was disabled since.
+    pub(crate) fn is_protected(&self) -> bool {
+        self.protected
+    }
+
+    /// Register a guard, making the pages accessible with `prot` if it is the first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the access to the pages cannot be changed, since the secret would be
+    /// unreadable.
+    pub(crate) fn expose(&self, prot: Prot) {
+        self.lock();
+
+        let exposed = self.disabled.load(Ordering::Relaxed)
+            || self.guards.load(Ordering::Relaxed) > 0
+            || unsafe { self.pages.protect(prot) };
+        if exposed {
+            self.guards.fetch_add(1, Ordering::Relaxed);
+        }
+        if prot == Prot::ReadWrite {
+            // The guard may change the length of the secret.
+            self.len.store(UNRECORDED, Ordering::Relaxed);
+        }
+
+        self.unlock();
+
+        if !exposed {
+            panic!("Unable to mprotect secret");
+        }
+    }
+
+    /// Unregister a guard, making the pages inaccessible if it was the last.
+    pub(crate) fn conceal(&self) {
+        self.lock();
+
+        let guards = self.guards.fetch_sub(1, Ordering::Relaxed);
+        if guards == 1 && !self.disabled.load(Ordering::Relaxed) {
+            // Failing to protect again only weakens the protection, the secret is
+            // still readable by its owner.
+            unsafe { self.pages.protect(Prot::NoAccess) };
+        }
+
+        self.unlock();
+    }
+
+    /// Length of the secret as returned by `measure`, which runs while the pages are
+    /// readable.
+    ///
+    /// The length is recorded, so the pages are only exposed to measure it the first
+    /// time and again after a mutable guard or `wipe_all`, until then it is read without
+    /// `mprotect`.
+    pub(crate) fn len(&self, measure: impl FnOnce() -> usize) -> usize {
+        if self.disabled.load(Ordering::Relaxed) {
+            return measure();
+        }
+
+        let wipes = registry::wipes();
+        let len = self.len.load(Ordering::Relaxed);
+        if len != UNRECORDED && self.len_wipes.load(Ordering::Relaxed) == wipes {
+            return len;
+        }
+
+        self.expose(Prot::ReadOnly);
+        let len = measure();
+        self.len.store(len, Ordering::Relaxed);
+        self.len_wipes.store(wipes, Ordering::Relaxed);
+        self.conceal();
+
+        len
+    }
+
+    /// Make the pages readable and writable for good, for references that outlive any
+    /// guard.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the access to the pages cannot be changed.
+    #[cfg(any(feature = "deref", feature = "secrecy-compat"))]
+    pub(crate) fn disable(&self) {
+        self.lock();
+
+        let disabled = self.disabled.load(Ordering::Relaxed)
+            || unsafe { self.pages.protect(Prot::ReadWrite) };
+        self.disabled.store(disabled, Ordering::Relaxed);
+
+        self.unlock();
+
+        if !disabled {
+            panic!("Unable to mprotect secret");
+        }
+    }
+
+    fn lock(&self) {
+        while self
+            .busy
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+    }
+
+    fn unlock(&self) {
+        self.busy.store(false, Ordering::Release);
+    }
+}
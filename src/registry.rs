@@ -0,0 +1,222 @@
+//! Process-wide registry of live secrets, so they can all be wiped at once before the
+//! process exits, see [`wipe_all`].
+//!
+//! Without the `registry` feature a secret carries no entry and registering it is an
+//! empty inline function.
+
+use crate::SecretBox;
+use zeroize::Zeroize;
+
+/// Entry of a secret in the registry, empty without the `registry` feature.
+pub(crate) struct Registration {
+    #[cfg(feature = "registry")]
+    entry: Option<&'static imp::Entry>,
+}
+
+impl Registration {
+    /// Not registered yet, see [`register`].
+    pub(crate) const NONE: Self = Self {
+        #[cfg(feature = "registry")]
+        entry: None,
+    };
+}
+
+/// Add a newly created secret to the registry.
+#[inline(always)]
+pub(crate) fn register<S: Zeroize>(_secret: &mut SecretBox<S>) {
+    #[cfg(feature = "registry")]
+    {
+        let pages = _secret
+            .protection
+            .as_ref()
+            .filter(|protection| protection.is_protected())
+            .map_or((core::ptr::null_mut(), 0), |protection| {
+                (protection.pages().as_ptr(), protection.pages().len())
+            });
+
+        _secret.registration.entry =
+            Some(imp::register(_secret.inner_secret.as_ptr().cast(), imp::wipe::<S>, pages));
+    }
+}
+
+/// Remove a secret from the registry, before it is dropped or handed over.
+#[inline(always)]
+pub(crate) fn unregister<S: Zeroize>(secret: &mut SecretBox<S>) {
+    let _registration = core::mem::replace(&mut secret.registration, Registration::NONE);
+
+    #[cfg(feature = "registry")]
+    if let Some(entry) = _registration.entry {
+        imp::unregister(entry);
+    }
+}
+
+/// Number of calls to [`wipe_all`] so far, always zero without the `registry` feature.
+#[inline(always)]
+pub(crate) fn wipes() -> usize {
+    #[cfg(feature = "registry")]
+    return imp::WIPES.load(core::sync::atomic::Ordering::Acquire);
+
+    #[cfg(not(feature = "registry"))]
+    0
+}
+
+/// Zeroize every live secret of the process, e.g. from a `SIGTERM` handler or a panic
+/// hook right before exiting.
+///
+/// Every [`SecretBox`], and the types built on it, is zeroized through its `Zeroize`
+/// impl where it lives, including heap buffers the impl clears. The pages of protected
+/// secrets are made readable and writable to do so, until the next guard exposing the
+/// secret is dropped. Secrets stay valid, and are still zeroized and unlocked again when
+/// dropped.
+///
+/// This only loads atomics, calls `mprotect` and runs the `Zeroize` impls of the
+/// secrets, it never allocates nor takes a lock, so it can run in a signal handler as
+/// long as those impls don't either. The impls of `zeroize` for byte arrays, `Vec` and
+/// `String` don't. `mprotect` is not on the POSIX list of async-signal-safe functions,
+/// but is a plain system call where the crate protects pages.
+///
+/// # Safety
+///
+/// No secret may be accessed concurrently, by another thread or by the code the signal
+/// interrupted, which would race with zeroizing it. Call it as the process is about
+/// to exit, e.g. followed by `_exit`, since nothing stops other threads from going on
+/// with zeroed secrets. A secret created or dropped concurrently may be missed.
+///
+/// # Examples
+///
+/// ```
+/// use shush::{ExposeSecret, SecretBox};
+///
+/// let key = SecretBox::new(Box::new([7u8; 32]));
+///
+/// unsafe { shush::wipe_all() };
+/// assert_eq!(*key.expose_secret(), [0; 32]);
+/// ```
+#[cfg(feature = "registry")]
+pub unsafe fn wipe_all() {
+    imp::wipe_all();
+}
+
+#[cfg(feature = "registry")]
+mod imp {
+    use crate::page;
+    use alloc::boxed::Box;
+    use core::{
+        mem, ptr,
+        sync::atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+    };
+    use zeroize::Zeroize;
+
+    /// States of an [`Entry`].
+    const FREE: u8 = 0;
+    const CLAIMED: u8 = 1;
+    const LIVE: u8 = 2;
+
+    /// First entry of a list that only grows, entries are reused once free instead of
+    /// freed, so [`wipe_all`] can walk it without a lock.
+    static HEAD: AtomicPtr<Entry> = AtomicPtr::new(ptr::null_mut());
+
+    /// See [`super::wipes`].
+    pub(super) static WIPES: AtomicUsize = AtomicUsize::new(0);
+
+    /// One secret of the registry, only read by [`wipe_all`] while it is [`LIVE`].
+    pub(super) struct Entry {
+        state: AtomicU8,
+        secret: AtomicPtr<u8>,
+        /// `unsafe fn(*mut u8)` zeroizing the secret, see [`wipe`].
+        wipe: AtomicPtr<()>,
+        /// Pages to make writable before wiping a protected secret, or null.
+        pages: AtomicPtr<u8>,
+        pages_len: AtomicUsize,
+        /// Next entry, fixed before the entry is added to the list.
+        next: *mut Entry,
+    }
+
+    /// Zeroize the secret of type `S` at `secret`.
+    ///
+    /// # Safety
+    ///
+    /// `secret` must point to a live `S` that nothing else accesses.
+    pub(super) unsafe fn wipe<S: Zeroize>(secret: *mut u8) {
+        (*secret.cast::<S>()).zeroize();
+    }
+
+    pub(super) fn register(
+        secret: *mut u8,
+        wipe: unsafe fn(*mut u8),
+        (pages, pages_len): (*mut u8, usize),
+    ) -> &'static Entry {
+        let entry = claim();
+
+        entry.secret.store(secret, Ordering::Relaxed);
+        entry.wipe.store(wipe as *mut (), Ordering::Relaxed);
+        entry.pages.store(pages, Ordering::Relaxed);
+        entry.pages_len.store(pages_len, Ordering::Relaxed);
+        entry.state.store(LIVE, Ordering::Release);
+
+        entry
+    }
+
+    pub(super) fn unregister(entry: &Entry) {
+        entry.state.store(FREE, Ordering::Release);
+    }
+
+    /// Claim a free entry, or add a new one to the list if none is free, so the list
+    /// only grows to the most secrets alive at once.
+    fn claim() -> &'static Entry {
+        let mut next = HEAD.load(Ordering::Acquire);
+
+        while let Some(entry) = unsafe { next.as_ref() } {
+            if entry
+                .state
+                .compare_exchange(FREE, CLAIMED, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                return entry;
+            }
+
+            next = entry.next;
+        }
+
+        let entry = Box::leak(Box::new(Entry {
+            state: AtomicU8::new(CLAIMED),
+            secret: AtomicPtr::new(ptr::null_mut()),
+            wipe: AtomicPtr::new(ptr::null_mut()),
+            pages: AtomicPtr::new(ptr::null_mut()),
+            pages_len: AtomicUsize::new(0),
+            next: ptr::null_mut(),
+        }));
+        let mut head = HEAD.load(Ordering::Relaxed);
+
+        loop {
+            entry.next = head;
+
+            match HEAD.compare_exchange_weak(head, entry, Ordering::Release, Ordering::Relaxed) {
+                Ok(_) => return entry,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    pub(super) unsafe fn wipe_all() {
+        let mut next = HEAD.load(Ordering::Acquire);
+
+        while let Some(entry) = next.as_ref() {
+            if entry.state.load(Ordering::Acquire) == LIVE {
+                let pages_len = entry.pages_len.load(Ordering::Relaxed);
+                if pages_len != 0 {
+                    page::unprotect(entry.pages.load(Ordering::Relaxed), pages_len);
+                }
+
+                let wipe = entry.wipe.load(Ordering::Relaxed);
+                let wipe = mem::transmute::<*mut (), unsafe fn(*mut u8)>(wipe);
+                wipe(entry.secret.load(Ordering::Relaxed));
+            }
+
+            next = entry.next;
+        }
+
+        // Lengths recorded by protected secrets are stale now.
+        WIPES.fetch_add(1, Ordering::Release);
+    }
+}
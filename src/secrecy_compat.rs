@@ -0,0 +1,93 @@
+//! Bridge to the [`secrecy`] crate, so secrets can be passed to libraries built on it.
+
+use crate::{SecretBox, SecretString};
+use zeroize::Zeroize;
+
+/// Expose the secret through a plain reference, as `secrecy` expects.
+///
+/// The reference lives as long as the box instead of a guard. A protected secret, see
+/// [`SecretBox::new_protected`], therefore stays accessible for the rest of its life
+/// once exposed this way.
+impl<S: Zeroize> secrecy::ExposeSecret<S> for SecretBox<S> {
+    fn expose_secret(&self) -> &S {
+        self.assert_alive();
+
+        if let Some(protection) = &self.protection {
+            protection.disable();
+        }
+
+        unsafe { self.inner_secret.as_ref() }
+    }
+}
+
+/// Move a `secrecy` string into locked memory.
+///
+/// `secrecy` doesn't lock its memory, so the string is copied into a locked buffer and
+/// the original is zeroized when it is dropped right after.
+///
+/// # Examples
+///
+/// ```
+/// use secrecy::ExposeSecret;
+/// use shush::SecretString;
+///
+/// let password = secrecy::SecretString::from("hunter2");
+/// let locked = SecretString::from(password);
+///
+/// let back = secrecy::SecretString::from(locked);
+/// assert_eq!(back.expose_secret(), "hunter2");
+/// ```
+impl From<secrecy::SecretString> for SecretString {
+    fn from(source: secrecy::SecretString) -> Self {
+        Self::copy_locked(secrecy::ExposeSecret::expose_secret(&source))
+    }
+}
+
+/// Hand the string to a library built on `secrecy`.
+///
+/// The copy is zeroized on drop by `secrecy`, but it lives in memory that isn't locked.
+impl From<SecretString> for secrecy::SecretString {
+    fn from(source: SecretString) -> Self {
+        source.with_secret(|secret| secrecy::SecretString::from(secret.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+
+    fn first_byte(secret: &impl secrecy::ExposeSecret<[u8; 32]>) -> u8 {
+        secret.expose_secret()[0]
+    }
+
+    #[test]
+    fn test_secrecy_expose_secret() {
+        let secret = SecretBox::new(Box::new([5u8; 32]));
+
+        assert_eq!(first_byte(&secret), 5);
+    }
+
+    #[test]
+    fn test_secrecy_expose_protected_secret() {
+        let mut secret = SecretBox::new_protected(Box::new([5u8; 32]));
+
+        assert_eq!(first_byte(&secret), 5);
+
+        // Guards keep working once the protection is disabled.
+        crate::ExposeSecret::expose_secret_mut(&mut secret)[0] = 6;
+        assert_eq!(first_byte(&secret), 6);
+    }
+
+    #[test]
+    fn test_secrecy_string_round_trip() {
+        let locked = SecretString::from(secrecy::SecretString::from("hunter2"));
+
+        assert_eq!(&*crate::ExposeSecret::expose_secret(&locked), "hunter2");
+        assert!(locked.is_locked() || !crate::lock::ENABLED);
+        assert!(locked.locked_heap.is_some());
+
+        let back = secrecy::SecretString::from(locked);
+        assert_eq!(secrecy::ExposeSecret::expose_secret(&back), "hunter2");
+    }
+}
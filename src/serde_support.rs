@@ -0,0 +1,221 @@
+//! [`serde`] support for secrets.
+//!
+//! Secrets deserialize straight into a locked box. Serializing redacts the secret unless
+//! the `serialize-plaintext` feature is enabled, which should only be used for trusted
+//! sinks such as an encrypted vault file.
+
+use crate::SecretBox;
+#[cfg(feature = "serialize-plaintext")]
+use crate::ExposeSecret;
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use zeroize::Zeroize;
+
+/// Most bytes locked upfront for a sequence of bytes, whatever length it claims.
+const MAX_PREALLOC: usize = 1 << 20;
+
+/// Placeholder written instead of the secret when serializing without
+/// `serialize-plaintext`.
+#[cfg(not(feature = "serialize-plaintext"))]
+const REDACTED: &str = "[REDACTED]";
+
+impl<'de, S> Deserialize<'de> for SecretBox<S>
+where
+    S: Zeroize + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Deserialize straight into the heap so the value is moved into the locked box
+        // without another copy.
+        Box::<S>::deserialize(deserializer).map(Self::new)
+    }
+}
+
+impl SecretBox<Vec<u8>> {
+    /// Deserialize bytes straight into a locked buffer, for use with
+    /// `#[serde(deserialize_with = "SecretBox::deserialize_locked")]`.
+    ///
+    /// Deserializing a `SecretBox<Vec<u8>>` the usual way stages the bytes in a `Vec`
+    /// nobody locked or zeroizes. Here bytes handed over at once are copied into a buffer
+    /// locked for their length, and a staging `Vec` given by the format is zeroized.
+    /// Sequences of bytes, e.g. a JSON array, are appended to a locked buffer that grows
+    /// as needed, zeroizing the smaller buffers it outgrows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use shush::{ExposeSecret, SecretBox};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     #[serde(deserialize_with = "SecretBox::deserialize_locked")]
+    ///     key: SecretBox<Vec<u8>>,
+    /// }
+    ///
+    /// let config: Config = serde_json::from_str(r#"{"key":[1,2,3]}"#).unwrap();
+    /// assert_eq!(*config.key.expose_secret(), [1, 2, 3]);
+    /// ```
+    pub fn deserialize_locked<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_byte_buf(LockedBytesVisitor)
+    }
+}
+
+/// Visitor writing bytes into locked memory, see [`SecretBox::deserialize_locked`].
+struct LockedBytesVisitor;
+
+impl<'de> Visitor<'de> for LockedBytesVisitor {
+    type Value = SecretBox<Vec<u8>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a byte array")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        let mut secret = SecretBox::try_zeroed(bytes.len()).map_err(E::custom)?;
+        secret.with_secret_mut(|secret| secret.copy_from_slice(bytes));
+
+        Ok(secret)
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, mut bytes: Vec<u8>) -> Result<Self::Value, E> {
+        let secret = self.visit_bytes(&bytes);
+        bytes.zeroize();
+
+        secret
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let capacity = seq.size_hint().unwrap_or(0).min(MAX_PREALLOC);
+        let mut secret = SecretBox::try_with_capacity(capacity).map_err(de::Error::custom)?;
+
+        // On error the bytes read so far are zeroized with the secret.
+        while let Some(byte) = seq.next_element::<u8>()? {
+            secret.try_reserve_locked(1).map_err(de::Error::custom)?;
+            secret.with_secret_mut(|secret| secret.push(byte));
+        }
+
+        Ok(secret)
+    }
+}
+
+impl<S> Serialize for SecretBox<S>
+where
+    S: Zeroize + Serialize,
+{
+    #[cfg(not(feature = "serialize-plaintext"))]
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+
+    #[cfg(feature = "serialize-plaintext")]
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        self.expose_secret().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{heap::assert_heap_locked, ExposeSecret, SecretString};
+    use serde::de::value::{BytesDeserializer, Error, SeqDeserializer};
+
+    #[derive(Deserialize, Serialize)]
+    struct Config {
+        user: String,
+        password: SecretString,
+    }
+
+    #[test]
+    fn test_deserialize_from_json() {
+        let config: Config =
+            serde_json::from_str(r#"{"user":"admin","password":"hunter2"}"#).unwrap();
+
+        assert_eq!(config.user, "admin");
+        assert_eq!(&*config.password.expose_str(), "hunter2");
+    }
+
+    #[test]
+    fn test_deserialize_array() {
+        let secret: SecretBox<[u8; 4]> = serde_json::from_str("[1,2,3,4]").unwrap();
+
+        assert_eq!(*secret.expose_secret(), [1, 2, 3, 4]);
+    }
+
+    #[derive(Deserialize)]
+    struct Keyfile {
+        #[serde(deserialize_with = "SecretBox::deserialize_locked")]
+        key: SecretBox<Vec<u8>>,
+    }
+
+    #[test]
+    fn test_deserialize_locked_from_sequence() {
+        let mut keyfile: Keyfile =
+            serde_json::from_str(r#"{"key":[1,2,3,4,5,6,7,8,9,10]}"#).unwrap();
+
+        assert_eq!(
+            *keyfile.key.expose_secret(),
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+        );
+        assert_heap_locked(&mut keyfile.key);
+    }
+
+    #[test]
+    fn test_deserialize_locked_from_sized_sequence() {
+        let deserializer = SeqDeserializer::<_, Error>::new([7u8; 64].into_iter());
+
+        let mut secret = SecretBox::deserialize_locked(deserializer).unwrap();
+
+        assert_eq!(*secret.expose_secret(), [7; 64]);
+        assert_heap_locked(&mut secret);
+    }
+
+    #[test]
+    fn test_deserialize_locked_from_bytes() {
+        let mut secret =
+            SecretBox::deserialize_locked(BytesDeserializer::<Error>::new(b"key")).unwrap();
+
+        assert_eq!(*secret.expose_secret(), b"key");
+        assert_heap_locked(&mut secret);
+
+        let keyfile: Keyfile = serde_json::from_str(r#"{"key":"key"}"#).unwrap();
+        assert_eq!(*keyfile.key.expose_secret(), b"key");
+    }
+
+    #[test]
+    fn test_deserialize_locked_rejects_non_bytes() {
+        assert!(serde_json::from_str::<Keyfile>(r#"{"key":[1,256]}"#).is_err());
+        assert!(serde_json::from_str::<Keyfile>(r#"{"key":7}"#).is_err());
+    }
+
+    #[cfg(not(feature = "serialize-plaintext"))]
+    #[test]
+    fn test_serialize_redacts() {
+        let config = Config {
+            user: "admin".to_string(),
+            password: SecretString::from("hunter2"),
+        };
+
+        let json = serde_json::to_string(&config).unwrap();
+
+        assert_eq!(json, r#"{"user":"admin","password":"[REDACTED]"}"#);
+    }
+
+    #[cfg(feature = "serialize-plaintext")]
+    #[test]
+    fn test_serialize_plaintext() {
+        let config = Config {
+            user: "admin".to_string(),
+            password: SecretString::from("hunter2"),
+        };
+
+        let json = serde_json::to_string(&config).unwrap();
+
+        assert_eq!(json, r#"{"user":"admin","password":"hunter2"}"#);
+    }
+}
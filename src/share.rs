@@ -0,0 +1,139 @@
+//! Splitting secrets into XOR shares.
+
+use crate::{lock, ExposeSecret, SecretBox};
+use alloc::vec::Vec;
+use rand_core::RngCore;
+use zeroize::Zeroize;
+
+impl<S: Zeroize + AsRef<[u8]>> SecretBox<S> {
+    /// Split the bytes of the secret into `n` shares of the same length, which all have
+    /// to be combined with [`SecretBox::combine_xor`] to recover it.
+    ///
+    /// All shares but one are random, the last one is the secret XORed with them. Any
+    /// `n - 1` shares reveal nothing about the secret. Every share is locked before it
+    /// is filled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero, or if the memory of a share cannot be locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::{ExposeSecret, SecretBox};
+    /// # use rand_core::SeedableRng;
+    /// # let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+    ///
+    /// let key = SecretBox::new(Box::new([7u8; 32]));
+    /// let shares = key.split_xor(3, &mut rng);
+    ///
+    /// let recovered = SecretBox::combine_xor(&shares);
+    /// assert_eq!(*recovered.expose_secret(), [7; 32]);
+    /// ```
+    pub fn split_xor(&self, n: usize, rng: &mut impl RngCore) -> Vec<SecretBox<Vec<u8>>> {
+        assert!(n > 0, "a secret needs at least one share");
+        let len = self.with_secret(|secret| secret.as_ref().len());
+
+        let mut shares: Vec<_> = (1..n)
+            .map(|_| {
+                let mut share = zeroed(len);
+                rng.fill_bytes(&mut share.expose_secret_mut());
+                share
+            })
+            .collect();
+
+        let mut last = zeroed(len);
+        self.with_secret(|secret| xor_into(&mut last, secret.as_ref()));
+        for share in &shares {
+            share.with_secret(|share| xor_into(&mut last, share));
+        }
+        shares.push(last);
+
+        shares
+    }
+}
+
+impl SecretBox<Vec<u8>> {
+    /// Recover a secret from all the shares [`SecretBox::split_xor`] made.
+    ///
+    /// The secret is combined in place in locked memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shares` is empty or the shares are of different lengths, or if the
+    /// memory of the secret cannot be locked.
+    pub fn combine_xor(shares: &[SecretBox<Vec<u8>>]) -> Self {
+        let len = shares.first().expect("no shares to combine").with_secret(Vec::len);
+        assert!(
+            shares.iter().all(|share| share.with_secret(Vec::len) == len),
+            "shares are of different lengths"
+        );
+
+        let mut secret = zeroed(len);
+        for share in shares {
+            share.with_secret(|share| xor_into(&mut secret, share));
+        }
+
+        secret
+    }
+}
+
+/// Create a secret of `len` zeroed bytes with its buffer locked.
+fn zeroed(len: usize) -> SecretBox<Vec<u8>> {
+    SecretBox::try_zeroed(len).unwrap_or_else(lock::failed)
+}
+
+/// XOR `bytes` into `secret` in place.
+fn xor_into(secret: &mut SecretBox<Vec<u8>>, bytes: &[u8]) {
+    for (byte, other) in secret.expose_secret_mut().iter_mut().zip(bytes) {
+        *byte ^= other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heap::assert_heap_locked;
+    use alloc::boxed::Box;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_split_combine_round_trip() {
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let key = SecretBox::<[u8; 32]>::generate(&mut rng);
+
+        let mut shares = key.split_xor(3, &mut rng);
+
+        assert_eq!(shares.len(), 3);
+        for share in &mut shares {
+            assert_eq!(share.with_secret(Vec::len), 32);
+            assert_ne!(*share.expose_secret(), *key.expose_secret());
+
+            assert_heap_locked(share);
+        }
+
+        let recovered = SecretBox::combine_xor(&shares);
+        assert_eq!(*recovered.expose_secret(), *key.expose_secret());
+
+        let partial = SecretBox::combine_xor(&shares[1..]);
+        assert_ne!(*partial.expose_secret(), *key.expose_secret());
+    }
+
+    #[test]
+    fn test_split_into_one_share() {
+        let key = SecretBox::new(Box::new(b"key".to_vec()));
+
+        let shares = key.split_xor(1, &mut ChaCha20Rng::seed_from_u64(42));
+
+        assert_eq!(*shares[0].expose_secret(), b"key");
+    }
+
+    #[test]
+    #[should_panic(expected = "shares are of different lengths")]
+    fn test_combine_different_lengths() {
+        let shares = [zeroed(4), zeroed(5)];
+
+        SecretBox::combine_xor(&shares);
+    }
+}
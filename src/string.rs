@@ -0,0 +1,293 @@
+//! Secret strings such as passwords and API keys.
+
+use crate::{heap::string_buffer, lock, ExposeSecret, SecretBox, SecretGuard};
+use alloc::{boxed::Box, string::String};
+use core::{
+    fmt::{self, Display},
+    mem,
+};
+#[cfg(feature = "std")]
+use std::env::{self, VarError};
+use zeroize::Zeroize;
+
+/// Secret string, zeroized on drop and redacted in `Debug`.
+pub type SecretString = SecretBox<String>;
+
+impl SecretString {
+    /// Expose the secret as a string slice.
+    pub fn expose_str(&self) -> SecretGuard<'_, str> {
+        SecretGuard::map(self.expose_secret(), String::as_str)
+    }
+
+    /// Length of the secret in bytes, without exposing it.
+    pub fn len(&self) -> usize {
+        self.secret_len(String::len)
+    }
+
+    /// Whether the secret is empty, without exposing it.
+    pub fn is_empty(&self) -> bool {
+        self.secret_len(String::len) == 0
+    }
+
+    /// Read the environment variable `key` into a string whose buffer is locked.
+    ///
+    /// The `String` returned by [`std::env::var`] is zeroized once copied. The variable
+    /// itself stays in the environment, see [`Self::take_env`] to remove it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use shush::SecretString;
+    ///
+    /// let token = SecretString::from_env("API_TOKEN").unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_env(key: &str) -> Result<Self, VarError> {
+        Ok(Self::copy_zeroizing(&mut env::var(key)?))
+    }
+
+    /// Same as [`Self::from_env`], then remove the variable from the environment of
+    /// the process so child processes don't inherit it.
+    ///
+    /// Removing the variable does not wipe the copy the C library may keep, and like
+    /// [`std::env::remove_var`] it is racy when other threads read the environment.
+    #[cfg(feature = "std")]
+    pub fn take_env(key: &str) -> Result<Self, VarError> {
+        let secret = Self::from_env(key)?;
+        env::remove_var(key);
+
+        Ok(secret)
+    }
+
+    /// Move `source` into a secret and lock its buffer in place, so a password read
+    /// into a `String` isn't left behind in a second allocation.
+    ///
+    /// If the buffer cannot be locked where it is, e.g. because its spare capacity
+    /// doesn't fit the lock limit, the string is copied into a buffer locked beforehand
+    /// and `source` is zeroized instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use shush::SecretString;
+    ///
+    /// let password = SecretString::from_string_zeroizing(String::from("hunter2"));
+    ///
+    /// assert_eq!(&*password.expose_str(), "hunter2");
+    /// ```
+    pub fn from_string_zeroizing(mut source: String) -> Self {
+        let mut secret = Self::new(Box::default());
+        secret.with_secret_mut(|secret| mem::swap(secret, &mut source));
+
+        match unsafe { secret.lock_heap(string_buffer) } {
+            Ok(()) => secret,
+            Err(_) => {
+                // Take the string back to copy it, `secret` now holds an empty one.
+                secret.with_secret_mut(|secret| mem::swap(secret, &mut source));
+                Self::copy_zeroizing(&mut source)
+            }
+        }
+    }
+
+    /// Copy `source` into a string whose buffer is locked before the copy, then
+    /// zeroize `source`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    pub(crate) fn copy_zeroizing(source: &mut String) -> Self {
+        let secret = Self::copy_locked(source);
+        source.zeroize();
+
+        secret
+    }
+
+    /// Copy `value` into a string whose buffer is locked before the copy, leaving the
+    /// caller to zeroize `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory of the secret cannot be locked.
+    pub(crate) fn copy_locked(value: &str) -> Self {
+        let mut secret = Self::new(Box::new(String::with_capacity(value.len())));
+        unsafe { secret.lock_heap(string_buffer) }.unwrap_or_else(lock::failed);
+        secret.with_secret_mut(|secret| secret.push_str(value));
+
+        secret
+    }
+}
+
+/// Mask printed in place of the secret by `Display`.
+const MASK: &str = "****";
+
+/// Display the secret as a fixed mask, `****`, or with the alternate flag `{:#}` as its
+/// length in characters, e.g. `<7 chars>`, for logs that need a hint of what was set.
+///
+/// The contents are never written. The mask is the same whatever the secret, while the
+/// length reveals how long it is.
+///
+/// # Examples
+///
+/// ```
+/// use shush::SecretString;
+///
+/// let password = SecretString::from("hunter2");
+///
+/// assert_eq!(format!("{password}"), "****");
+/// assert_eq!(format!("{password:#}"), "<7 chars>");
+/// ```
+impl Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "<{} chars>", self.with_secret(|secret| secret.chars().count()))
+        } else {
+            f.write_str(MASK)
+        }
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(source: String) -> Self {
+        Self::new(Box::new(source))
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(source: &str) -> Self {
+        Self::from(String::from(source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_string_from_literal() {
+        let secret = SecretString::from("hunter2");
+
+        assert_eq!(&*secret.expose_str(), "hunter2");
+        assert_eq!(*secret.expose_secret(), "hunter2");
+    }
+
+    #[test]
+    fn test_secret_string_from_string() {
+        let secret = SecretString::from(String::from("hunter2"));
+
+        assert_eq!(&*secret.expose_str(), "hunter2");
+    }
+
+    #[test]
+    fn test_secret_string_len() {
+        let secret = SecretString::from("pässword");
+
+        assert_eq!(secret.len(), 9);
+        assert!(!secret.is_empty());
+        assert!(SecretString::from("").is_empty());
+    }
+
+    #[test]
+    fn test_protected_secret_string_len_does_not_expose() {
+        let mut secret = SecretString::new_protected(Box::new(String::from("hunter2")));
+        assert_eq!(secret.len(), 7);
+
+        let protects = crate::page::PROTECTS.with(|protects| protects.get());
+        assert_eq!(secret.len(), 7);
+        assert!(!secret.is_empty());
+        assert_eq!(crate::page::PROTECTS.with(|protects| protects.get()), protects);
+
+        // A mutable guard may change the length, it is measured again.
+        secret.expose_secret_mut().push_str("!!");
+        assert_eq!(secret.len(), 9);
+    }
+
+    #[test]
+    fn test_secret_string_from_string_zeroizing_locks_in_place() {
+        let source = String::from("hunter2");
+        let buffer = source.as_ptr();
+
+        let secret = SecretString::from_string_zeroizing(source);
+
+        assert_eq!(&*secret.expose_str(), "hunter2");
+        assert_eq!(secret.with_secret(|secret| secret.as_ptr()), buffer);
+        assert_eq!(secret.locked_heap.as_ref().map(|heap| heap.ptr.cast_const()), Some(buffer));
+    }
+
+    #[test]
+    fn test_secret_string_copy_zeroizing_clears_source() {
+        let mut source = String::from("hunter2");
+        let (buffer, capacity) = (source.as_ptr(), source.capacity());
+
+        let secret = SecretString::copy_zeroizing(&mut source);
+
+        assert_eq!(&*secret.expose_str(), "hunter2");
+        assert!(secret.locked_heap.is_some());
+        assert!(source.is_empty());
+        let contents = unsafe { core::slice::from_raw_parts(buffer, capacity) };
+        assert!(contents.iter().all(|&byte| byte == 0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_secret_string_from_env() {
+        env::set_var("SHUSH_TEST_FROM_ENV", "hunter2");
+
+        let secret = SecretString::from_env("SHUSH_TEST_FROM_ENV").unwrap();
+
+        assert_eq!(&*secret.expose_str(), "hunter2");
+        assert!(secret.locked_heap.is_some());
+        assert_eq!(env::var("SHUSH_TEST_FROM_ENV").unwrap(), "hunter2");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_secret_string_take_env() {
+        env::set_var("SHUSH_TEST_TAKE_ENV", "hunter2");
+
+        let secret = SecretString::take_env("SHUSH_TEST_TAKE_ENV").unwrap();
+
+        assert_eq!(&*secret.expose_str(), "hunter2");
+        assert_eq!(env::var("SHUSH_TEST_TAKE_ENV"), Err(VarError::NotPresent));
+        assert_eq!(
+            SecretString::take_env("SHUSH_TEST_TAKE_ENV").unwrap_err(),
+            VarError::NotPresent
+        );
+    }
+
+    #[test]
+    fn test_secret_string_display_mask() {
+        let secret = SecretString::from("hunter2");
+        let display = format!("{secret}");
+
+        assert_eq!(display, "****");
+        assert_eq!(format!("{}", SecretString::from("")), display);
+        assert!(!display.chars().any(|c| "hunter2".contains(c)));
+    }
+
+    #[test]
+    fn test_secret_string_display_length() {
+        let secret = SecretString::from("pässword");
+        let display = format!("{secret:#}");
+
+        assert_eq!(display, "<8 chars>");
+        assert!(!display.contains('p') && !display.contains('ä'));
+        assert_eq!(format!("{:#}", SecretString::from("")), "<0 chars>");
+    }
+
+    #[test]
+    fn test_secret_string_debug_is_redacted() {
+        let secret = SecretString::from("hunter2");
+        let debug = format!("{secret:?}");
+
+        assert!(debug.ends_with("([REDACTED])"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
@@ -0,0 +1,135 @@
+//! Lifecycle events of secrets for auditing, emitted through `tracing`.
+//!
+//! Events carry the type name and size of the secret, never its value. Without the
+//! `tracing` feature every hook is an empty inline function.
+
+use crate::SecretBox;
+use zeroize::Zeroize;
+
+/// A secret was created, or reclaimed with [`SecretBox::reclaim`].
+#[inline(always)]
+pub(crate) fn created<S: Zeroize>(_secret: &SecretBox<S>) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        target: "shush",
+        secret = core::any::type_name::<S>(),
+        bytes = _secret.len_bytes(),
+        "secret created"
+    );
+}
+
+/// A secret was exposed through a guard.
+#[inline(always)]
+pub(crate) fn exposed<S: Zeroize>(_secret: &SecretBox<S>) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(
+        target: "shush",
+        secret = core::any::type_name::<S>(),
+        bytes = _secret.len_bytes(),
+        "secret exposed"
+    );
+}
+
+/// A secret is about to be zeroized and freed.
+#[inline(always)]
+pub(crate) fn dropped<S: Zeroize>(_secret: &SecretBox<S>) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        target: "shush",
+        secret = core::any::type_name::<S>(),
+        bytes = _secret.len_bytes(),
+        "secret dropped"
+    );
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::{ExposeSecret, SecretBox, SecretString};
+    use std::{
+        fmt::{Debug, Write},
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    /// Subscriber recording every event of the crate as a line of text.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    /// Fields of an event, formatted as `message field=value...`.
+    struct Line(String);
+
+    impl Visit for Line {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            write!(self.0, " {field}={value}").unwrap();
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            match field.name() {
+                "message" => write!(self.0, "{value:?}").unwrap(),
+                name => write!(self.0, " {name}={value:?}").unwrap(),
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "shush"
+        }
+
+        fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut line = Line(String::new());
+            event.record(&mut line);
+            self.0.lock().unwrap().push(line.0);
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn test_lifecycle_events_omit_secret() {
+        let recorder = Recorder::default();
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let key = SecretBox::new(Box::new([0x42u8; 32]));
+            assert_eq!(key.expose_secret()[0], 0x42);
+        });
+
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(
+            *events,
+            [
+                "secret created secret=[u8; 32] bytes=32",
+                "secret exposed secret=[u8; 32] bytes=32",
+                "secret dropped secret=[u8; 32] bytes=32",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_len_is_not_an_exposure() {
+        let recorder = Recorder::default();
+
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let password = SecretString::new_protected(Box::new(String::from("hunter2")));
+            assert_eq!(password.len(), 7);
+            assert!(!password.is_empty());
+        });
+
+        let events = recorder.0.lock().unwrap();
+        assert!(events.iter().all(|event| !event.starts_with("secret exposed")));
+        assert_eq!(events.len(), 2);
+    }
+}
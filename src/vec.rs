@@ -0,0 +1,91 @@
+//! Secret byte vectors for binary key material.
+
+use crate::{ExposeSecret, SecretBox, SecretGuard, SecretGuardMut};
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt::{self, Debug};
+
+/// Secret vector of bytes, zeroized on drop and redacted in `Debug`.
+pub struct SecretVec(pub(crate) SecretBox<Vec<u8>>);
+
+impl SecretVec {
+    /// Expose the secret as a byte slice.
+    pub fn expose_as_slice(&self) -> SecretGuard<'_, [u8]> {
+        SecretGuard::map(self.0.expose_secret(), Vec::as_slice)
+    }
+
+    /// Number of bytes in the secret, without exposing it.
+    pub fn len(&self) -> usize {
+        self.0.secret_len(Vec::len)
+    }
+
+    /// Whether the secret holds no bytes, without exposing it.
+    pub fn is_empty(&self) -> bool {
+        self.0.secret_len(Vec::len) == 0
+    }
+}
+
+impl From<Vec<u8>> for SecretVec {
+    fn from(source: Vec<u8>) -> Self {
+        Self(SecretBox::new(Box::new(source)))
+    }
+}
+
+impl Debug for SecretVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretVec({})", self.0.debug_label)
+    }
+}
+
+impl ExposeSecret<Vec<u8>> for SecretVec {
+    fn expose_secret(&self) -> SecretGuard<'_, Vec<u8>> {
+        self.0.expose_secret()
+    }
+
+    fn expose_secret_mut(&mut self) -> SecretGuardMut<'_, Vec<u8>> {
+        self.0.expose_secret_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_vec_from_vec() {
+        let secret = SecretVec::from(vec![1, 2, 3]);
+
+        assert_eq!(secret.len(), 3);
+        assert!(!secret.is_empty());
+        assert_eq!(*secret.expose_secret(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_secret_vec_expose_as_slice() {
+        let secret = SecretVec::from(vec![1, 2, 3]);
+        let guard = secret.expose_as_slice();
+
+        assert_eq!(&guard[1..], &[2, 3]);
+    }
+
+    #[test]
+    fn test_secret_vec_len_matches_bytes() {
+        let secret = SecretVec::from(vec![0; 100]);
+
+        assert_eq!(secret.len(), 100);
+        assert_eq!(secret.0.len_bytes(), size_of::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_secret_vec_empty() {
+        let secret = SecretVec::from(Vec::new());
+
+        assert!(secret.is_empty());
+    }
+
+    #[test]
+    fn test_secret_vec_debug_is_redacted() {
+        let secret = SecretVec::from(vec![0xde, 0xad]);
+
+        assert_eq!(format!("{secret:?}"), "SecretVec([REDACTED])");
+    }
+}
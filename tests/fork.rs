@@ -0,0 +1,27 @@
+#![cfg(all(target_os = "linux", feature = "dontfork"))]
+
+use shush::{ExposeSecret, SecretBox};
+
+#[test]
+fn test_forked_child_cannot_read_secret() {
+    let secret_box = SecretBox::new(Box::new([0x42u8; 32]));
+    let secret_ptr = secret_box.expose_secret().as_ptr();
+
+    unsafe {
+        let pid = libc::fork();
+        assert!(pid >= 0, "fork failed");
+
+        if pid == 0 {
+            // The page is not mapped in the child, so this read must fault.
+            let _ = std::ptr::read_volatile(secret_ptr);
+            libc::_exit(0);
+        }
+
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+        assert!(libc::WIFSIGNALED(status));
+        assert_eq!(libc::WTERMSIG(status), libc::SIGSEGV);
+    }
+
+    assert_eq!(secret_box.expose_secret()[0], 0x42);
+}
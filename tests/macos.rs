@@ -0,0 +1,25 @@
+#![cfg(all(target_os = "macos", feature = "mlock"))]
+
+use shush::{ExposeSecret, SecretBox};
+
+#[test]
+fn test_small_secret_is_locked() {
+    let secret_box = SecretBox::try_new(Box::new([0x42u8; 32])).unwrap();
+
+    assert!(secret_box.is_locked());
+    assert_eq!(*secret_box.expose_secret(), [0x42; 32]);
+
+    drop(secret_box);
+}
+
+#[test]
+fn test_protected_and_guarded_secrets_are_locked() {
+    let protected = SecretBox::try_new_protected(Box::new([1u8; 32])).unwrap();
+    let guarded = SecretBox::try_new_guarded(Box::new([2u8; 32])).unwrap();
+
+    assert!(protected.is_locked() && guarded.is_locked());
+    assert_eq!(
+        protected.expose_secret()[31] + guarded.expose_secret()[31],
+        3
+    );
+}
@@ -0,0 +1,292 @@
+#![cfg(all(target_os = "linux", feature = "mlock"))]
+
+use shush::{ExposeSecret, SecretBox};
+
+/// Run `f` in a forked child and return whether it returned `true`.
+fn in_child(f: impl FnOnce() -> bool) -> bool {
+    unsafe {
+        let pid = libc::fork();
+        assert!(pid >= 0, "fork failed");
+
+        if pid == 0 {
+            libc::_exit(if f() { 0 } else { 1 });
+        }
+
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+        assert!(libc::WIFEXITED(status), "child did not exit");
+
+        libc::WEXITSTATUS(status) == 0
+    }
+}
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Kilobytes locked in the mapping holding `ptr`, from `/proc/self/smaps`.
+fn locked_kb(ptr: *const u8) -> usize {
+    smaps_field(ptr, "Locked:").trim_end_matches("kB").trim().parse().unwrap()
+}
+
+/// Whether the mapping holding `ptr` is locked on fault, from its `VmFlags`.
+fn locked_on_fault(ptr: *const u8) -> bool {
+    smaps_field(ptr, "VmFlags:").split_whitespace().any(|flag| flag == "lf")
+}
+
+/// Value of `field` for the mapping holding `ptr`, from `/proc/self/smaps`.
+fn smaps_field(ptr: *const u8, field: &str) -> String {
+    let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+    let addr = ptr as usize;
+    let mut in_mapping = false;
+
+    for line in smaps.lines() {
+        if let Some(value) = line.strip_prefix(field) {
+            if in_mapping {
+                return value.to_owned();
+            }
+        } else if let Some((start, end)) = line.split(' ').next().unwrap().split_once('-') {
+            // Mappings start with their address range, e.g. `7f00-7f10 rw-p ...`.
+            let parse = |addr| usize::from_str_radix(addr, 16).unwrap();
+            in_mapping = (parse(start)..parse(end)).contains(&addr);
+        }
+    }
+
+    panic!("no mapping holds {ptr:p}");
+}
+
+/// Run `f` in a forked child and return the signal that killed it, if any.
+#[cfg(feature = "abort-on-lock-failure")]
+fn signal_in_child(f: impl FnOnce()) -> Option<libc::c_int> {
+    unsafe {
+        let pid = libc::fork();
+        assert!(pid >= 0, "fork failed");
+
+        if pid == 0 {
+            f();
+            libc::_exit(0);
+        }
+
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+
+        libc::WIFSIGNALED(status).then(|| libc::WTERMSIG(status))
+    }
+}
+
+/// Make every further `mlock` of the current process fail.
+fn forbid_memlock() {
+    unsafe {
+        // `CAP_IPC_LOCK` ignores the limit, so drop every capability first in case the
+        // tests run as root.
+        let header = [0x2008_0522u32, 0];
+        let data = [0u32; 6];
+        libc::syscall(libc::SYS_capset, header.as_ptr(), data.as_ptr());
+
+        let limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        assert_eq!(libc::setrlimit(libc::RLIMIT_MEMLOCK, &limit), 0);
+    }
+}
+
+#[test]
+fn test_try_clone_surfaces_lock_failure() {
+    assert!(in_child(|| {
+        let secret_box = SecretBox::new(Box::new([0x42u8; 32]));
+        forbid_memlock();
+
+        secret_box
+            .try_clone()
+            .is_err_and(|err| err.size() == page_size() && err.os_error().raw_os_error().is_some())
+    }));
+}
+
+#[test]
+fn test_try_clone() {
+    let secret_box = SecretBox::new(Box::new([0x42u8; 32]));
+
+    let clone = secret_box.try_clone().unwrap();
+
+    assert_eq!(*clone.expose_secret(), [0x42; 32]);
+}
+
+#[cfg(feature = "prefault")]
+#[test]
+fn test_prefaulted_secret_is_resident() {
+    // Many pages, while staying under the default `RLIMIT_MEMLOCK` of 64 KiB.
+    const LEN: usize = 32 << 10;
+
+    let secret_box = SecretBox::<[u8; LEN]>::new_array_zeroed();
+    let secret_ptr = secret_box.expose_secret().as_ptr();
+
+    let page_size = page_size();
+    let start = secret_ptr as usize & !(page_size - 1);
+    let end = (secret_ptr as usize + LEN).next_multiple_of(page_size);
+    let mut residency = vec![0u8; (end - start) / page_size];
+
+    let ret = unsafe { libc::mincore(start as *mut _, end - start, residency.as_mut_ptr()) };
+
+    assert_eq!(ret, 0);
+    assert!(residency.iter().all(|&page| page & 1 == 1));
+}
+
+#[test]
+fn test_dropping_secret_keeps_neighbour_locked() {
+    let first = SecretBox::new(Box::new(1u64));
+    let second = SecretBox::new(Box::new(2u64));
+    let second_ptr = second.with_secret(|secret| secret as *const u64).cast::<u8>();
+
+    assert!(locked_kb(second_ptr) > 0);
+
+    drop(first);
+
+    assert!(locked_kb(second_ptr) > 0);
+    assert_eq!(*second.expose_secret(), 2);
+}
+
+#[test]
+fn test_secret_zeroized_before_unlock() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use zeroize::Zeroize;
+
+    static LOCKED_KB_AT_ZEROIZE: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+    struct Probe([u8; 32]);
+
+    impl Zeroize for Probe {
+        fn zeroize(&mut self) {
+            LOCKED_KB_AT_ZEROIZE.store(locked_kb(self.0.as_ptr()), Ordering::SeqCst);
+            self.0.zeroize();
+        }
+    }
+
+    let secret_box = SecretBox::new(Box::new(Probe([0x42; 32])));
+    let secret_ptr = secret_box.with_secret(|secret| secret.0.as_ptr());
+    drop(secret_box);
+
+    assert!(LOCKED_KB_AT_ZEROIZE.load(Ordering::SeqCst) > 0);
+    // Freed now, but the unlocked mapping is still there to look up.
+    assert_eq!(locked_kb(secret_ptr), 0);
+}
+
+/// Whether the kernel supports `mlock2(MLOCK_ONFAULT)`, tried on a mapping of its own
+/// so unlocking it can't unlock a secret.
+fn kernel_locks_on_fault() -> bool {
+    unsafe {
+        let len = page_size();
+        let page = libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        assert_ne!(page, libc::MAP_FAILED);
+
+        let supported = libc::syscall(libc::SYS_mlock2, page, len, libc::MLOCK_ONFAULT) == 0;
+        libc::munmap(page, len);
+
+        supported
+    }
+}
+
+#[test]
+fn test_new_on_fault_locks_on_fault_or_falls_back() {
+    let secret_box = SecretBox::new_on_fault(Box::new([0x42u8; 16 << 10]));
+    let secret_ptr = secret_box.expose_secret().as_ptr();
+
+    // Older kernels lock the pages upfront instead.
+    assert_eq!(locked_on_fault(secret_ptr), kernel_locks_on_fault());
+    // Moving the secret in touched every page it takes.
+    assert!(locked_kb(secret_ptr) >= 16);
+    assert_eq!(*secret_box.expose_secret(), [0x42; 16 << 10]);
+}
+
+#[test]
+fn test_clone_of_secret_locked_on_fault_is_locked_on_fault() {
+    let secret_box = SecretBox::new_on_fault(Box::new([0x42u8; 16 << 10]));
+    let clone = secret_box.clone();
+    let clone_ptr = clone.expose_secret().as_ptr();
+
+    assert_eq!(locked_on_fault(clone_ptr), kernel_locks_on_fault());
+    assert_eq!(*clone.expose_secret(), [0x42; 16 << 10]);
+}
+
+#[test]
+fn test_truncate_locked_unlocks_freed_pages() {
+    let page_size = page_size();
+    let mut secret_box = SecretBox::from_slice_zeroizing(&mut vec![0x42; 8 * page_size]);
+    let secret_ptr = secret_box.expose_secret().as_ptr();
+    let last_page = unsafe { secret_ptr.add(7 * page_size) };
+    assert!(locked_kb(last_page) > 0);
+
+    secret_box.truncate_locked(16);
+
+    assert_eq!(locked_kb(last_page), 0);
+    assert!(locked_kb(secret_ptr) > 0);
+    assert_eq!(*secret_box.expose_secret(), [0x42; 16]);
+}
+
+/// Value in kilobytes of `field` in `/proc/meminfo`.
+fn meminfo(field: &str) -> usize {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap();
+    let value = meminfo.lines().find_map(|line| line.strip_prefix(field)).unwrap();
+
+    value.trim_end_matches("kB").trim().parse().unwrap()
+}
+
+#[test]
+fn test_new_hugepages_uses_huge_pages_or_falls_back() {
+    // Only set up where huge pages are reserved, e.g. with `sysctl vm.nr_hugepages=1`.
+    let huge_pages_free = meminfo("HugePages_Free:") > 0;
+
+    let secret_box = SecretBox::new_hugepages(Box::new([0x42u8; 64]));
+    let secret_ptr = secret_box.expose_secret().as_ptr();
+    let page_kb: usize = smaps_field(secret_ptr, "KernelPageSize:")
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .unwrap();
+
+    match huge_pages_free {
+        true => assert_eq!(page_kb, meminfo("Hugepagesize:")),
+        false => assert_eq!(page_kb << 10, page_size()),
+    }
+    assert!(secret_box.is_locked());
+    assert_eq!(*secret_box.expose_secret(), [0x42; 64]);
+
+    drop(secret_box);
+}
+
+#[test]
+fn test_from_raw_locked_locks_in_place() {
+    let raw = Box::into_raw(Box::new([0x42u8; 64]));
+
+    let secret_box = unsafe { SecretBox::from_raw_locked(raw) };
+
+    assert!(locked_kb(raw.cast()) > 0);
+    assert_eq!(*secret_box.expose_secret(), [0x42; 64]);
+}
+
+#[cfg(feature = "abort-on-lock-failure")]
+#[test]
+fn test_lock_failure_aborts() {
+    let signal = signal_in_child(|| {
+        forbid_memlock();
+        let _ = std::panic::catch_unwind(|| SecretBox::new(Box::new([0x42u8; 32])));
+    });
+
+    assert_eq!(signal, Some(libc::SIGABRT));
+}
+
+#[cfg(feature = "abort-on-lock-failure")]
+#[test]
+fn test_try_new_still_returns_lock_failure() {
+    assert!(in_child(|| {
+        forbid_memlock();
+        SecretBox::try_new(Box::new([0x42u8; 32])).is_err()
+    }));
+}
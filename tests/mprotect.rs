@@ -0,0 +1,163 @@
+#![cfg(unix)]
+
+use shush::{ExposeSecret, SecretArc, SecretBox};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+/// Read `ptr` in a forked child and return the signal that killed it, if any.
+fn read_in_child(ptr: *const u8) -> Option<libc::c_int> {
+    unsafe {
+        let pid = libc::fork();
+        assert!(pid >= 0, "fork failed");
+
+        if pid == 0 {
+            let _ = std::ptr::read_volatile(ptr);
+            libc::_exit(0);
+        }
+
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+
+        libc::WIFSIGNALED(status).then(|| libc::WTERMSIG(status))
+    }
+}
+
+/// Assert that reading `ptr` in a forked child faults.
+fn assert_faults(ptr: *const u8) {
+    let signal = read_in_child(ptr);
+
+    assert!(
+        matches!(signal, Some(libc::SIGSEGV) | Some(libc::SIGBUS)),
+        "expected a fault, got {signal:?}"
+    );
+}
+
+#[test]
+fn test_read_outside_guard_faults() {
+    let secret_box = SecretBox::new_protected(Box::new([0x42u8; 32]));
+    let secret_ptr = secret_box.expose_secret().as_ptr();
+
+    assert_faults(secret_ptr);
+}
+
+// With `dontfork` the child never sees the pages, exposed or not.
+#[cfg(not(feature = "dontfork"))]
+#[test]
+fn test_read_inside_guard_succeeds() {
+    let secret_box = SecretBox::new_protected(Box::new([0x42u8; 32]));
+    let guard = secret_box.expose_secret();
+
+    assert_eq!(read_in_child(guard.as_ptr()), None);
+}
+
+#[test]
+fn test_read_past_guarded_secret_faults() {
+    let secret_box = SecretBox::new_guarded(Box::new([0x42u8; 32]));
+    let secret_end = secret_box.expose_secret().as_ptr_range().end;
+
+    assert_faults(secret_end);
+}
+
+#[cfg(not(feature = "dontfork"))]
+#[test]
+fn test_read_last_byte_of_guarded_secret_succeeds() {
+    let secret_box = SecretBox::new_guarded(Box::new([0x42u8; 32]));
+    let last = &secret_box.expose_secret()[31];
+
+    assert_eq!(read_in_child(last), None);
+}
+
+/// Future that is pending the first time it is polled, and ready after.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        match std::mem::replace(&mut self.0, true) {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        }
+    }
+}
+
+#[test]
+fn test_cancelled_async_exposure_conceals() {
+    let secret_box = SecretBox::new_protected(Box::new([0x42u8; 32]));
+    let secret_ptr = secret_box.with_secret(|secret| secret.as_ptr());
+
+    let mut context = Context::from_waker(Waker::noop());
+    let mut future = Box::pin(secret_box.with_secret_async(async |secret| {
+        YieldOnce(false).await;
+        secret[0]
+    }));
+
+    assert!(future.as_mut().poll(&mut context).is_pending());
+    #[cfg(not(feature = "dontfork"))]
+    assert_eq!(read_in_child(secret_ptr), None);
+
+    // Cancel the exposure halfway through.
+    drop(future);
+
+    assert_faults(secret_ptr);
+}
+
+#[test]
+fn test_read_after_shared_guards_dropped_faults() {
+    let secret = SecretArc::new_protected(Box::new([0x42u8; 32]));
+    let clone = secret.clone();
+
+    let ptr = {
+        let outer = secret.expose_secret();
+        let inner = clone.expose_secret();
+        assert_eq!(inner[0], outer[0]);
+        outer.as_ptr()
+    };
+
+    assert_faults(ptr);
+}
+
+#[cfg(not(feature = "dontfork"))]
+#[test]
+fn test_nested_shared_guards_keep_pages_readable() {
+    let secret = SecretArc::new_protected(Box::new([0x42u8; 32]));
+    let clone = secret.clone();
+
+    let outer = secret.expose_secret();
+    let ptr = outer.as_ptr();
+    {
+        let inner = clone.expose_secret();
+        assert_eq!(read_in_child(inner.as_ptr()), None);
+    }
+
+    // The outer guard is still alive, so dropping the inner one must not conceal.
+    assert_eq!(read_in_child(ptr), None);
+
+    drop(outer);
+    assert_faults(ptr);
+}
+
+#[cfg(not(feature = "dontfork"))]
+#[test]
+fn test_shared_guards_across_threads() {
+    let secret = SecretArc::new_protected(Box::new([0x42u8; 32]));
+    let guard = secret.expose_secret();
+    let ptr = guard.as_ptr();
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            let secret = secret.clone();
+            scope.spawn(move || {
+                for _ in 0..100 {
+                    assert_eq!(secret.expose_secret()[31], 0x42);
+                }
+            });
+        }
+    });
+
+    assert_eq!(read_in_child(ptr), None);
+
+    drop(guard);
+    assert_faults(ptr);
+}
@@ -0,0 +1,26 @@
+#![cfg(feature = "registry")]
+
+// `wipe_all` zeroizes every secret of the process, so this binary holds a single test
+// to keep it from racing with secrets of other tests.
+
+use shush::{ExposeSecret, SecretBox, SecretString};
+
+#[test]
+fn test_wipe_all_zeroizes_live_secrets() {
+    let key = SecretBox::new_protected(Box::new([7u8; 32]));
+    let password = SecretString::from("hunter2");
+    let protected = SecretString::new_protected(Box::new(String::from("hunter2")));
+    assert_eq!(protected.len(), 7);
+    let dropped = SecretBox::new(Box::new([9u8; 16]));
+    drop(dropped);
+
+    unsafe { shush::wipe_all() };
+
+    assert_eq!(*key.expose_secret(), [0; 32]);
+    assert!(password.expose_secret().is_empty());
+    assert!(protected.is_empty());
+
+    // Wiped secrets stay usable, and a freed entry is reused by the next secret.
+    let reused = SecretBox::new(Box::new([1u8; 8]));
+    assert_eq!(*reused.expose_secret(), [1; 8]);
+}
@@ -0,0 +1,12 @@
+#[test]
+fn test_ui() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/key_*.rs");
+
+    // Without `subtle` nothing implements `PartialEq`, so the error reads differently.
+    #[cfg(feature = "subtle")]
+    cases.compile_fail("tests/ui/eq_*.rs");
+
+    #[cfg(not(feature = "hash"))]
+    cases.compile_fail("tests/ui/hash_*.rs");
+}
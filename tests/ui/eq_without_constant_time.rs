@@ -0,0 +1,8 @@
+use shush::SecretBox;
+
+fn main() {
+    let a = SecretBox::new(Box::new(String::from("hunter2")));
+    let b = SecretBox::new(Box::new(String::from("hunter2")));
+
+    let _ = a == b;
+}
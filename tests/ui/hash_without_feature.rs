@@ -0,0 +1,8 @@
+use shush::SecretBox;
+use std::hash::Hash;
+
+fn fingerprint<T: Hash>(_: &T) {}
+
+fn main() {
+    fingerprint(&SecretBox::new(Box::new([0u8; 16])));
+}
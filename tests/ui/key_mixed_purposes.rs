@@ -0,0 +1,15 @@
+use shush::{KeyPurpose, SecretKey};
+
+enum Signing {}
+impl KeyPurpose for Signing {}
+
+enum Encryption {}
+impl KeyPurpose for Encryption {}
+
+fn sign(_key: &SecretKey<Signing>) {}
+
+fn main() {
+    let key = SecretKey::<Encryption>::new(Box::new([7; 32]));
+
+    sign(&key);
+}